@@ -0,0 +1,66 @@
+// Community library imports
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion};
+use criterion::measurement::WallTime;
+use image::Rgba;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use spade::delaunay::{DelaunayLocateStructure, DelaunayTreeLocate, DelaunayWalkLocate};
+
+// Crate imports
+use adaptri::locate::{insert_points, InsertionOrder};
+use adaptri::point::Point;
+use adaptri::Mesh;
+
+// Side length of the square the points are placed in, like a large image
+const SIZE: f32 = 4000.0;
+
+// Random points in the square, in the order they were drawn, like the
+// centroids found by a refinement all over the image
+fn random_points(n_points: usize) -> Vec<Point> {
+    let mut rng = StdRng::seed_from_u64(42);
+    (0..n_points)
+        .map(|_| Point::new(rng.gen::<f32>() * SIZE, rng.gen::<f32>() * SIZE, Rgba([0.0, 0.0, 0.0, 1.0])))
+        .collect()
+}
+
+// Inserts the points into a mesh of the corners of the square
+fn bench_strategy<L: DelaunayLocateStructure<Point>>(
+    group: &mut BenchmarkGroup<WallTime>,
+    strategy: &str,
+    points: &[Point],
+    order: InsertionOrder,
+) {
+    let id = BenchmarkId::new(format!("{}/{:?}", strategy, order), points.len());
+    group.bench_with_input(id, points, |b, points| {
+        b.iter_batched(
+            || {
+                let mut mesh = Mesh::<L>::new();
+                for (x, y) in [(0.0, 0.0), (SIZE, 0.0), (0.0, SIZE), (SIZE, SIZE)] {
+                    mesh.insert(Point::new(x, y, Rgba([0.0; 4])));
+                }
+                (mesh, points.to_vec())
+            },
+            |(mut mesh, points)| {
+                insert_points(&mut mesh, points, order);
+                mesh
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_insertion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insertion");
+    group.sample_size(10);
+    for n_points in [10_000, 100_000] {
+        let points = random_points(n_points);
+        for order in [InsertionOrder::Unsorted, InsertionOrder::Hilbert, InsertionOrder::Morton] {
+            bench_strategy::<DelaunayWalkLocate>(&mut group, "walk", &points, order);
+            bench_strategy::<DelaunayTreeLocate<Point>>(&mut group, "tree", &points, order);
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_insertion);
+criterion_main!(benches);
@@ -0,0 +1,163 @@
+// Standard library imports
+use std::hint::black_box;
+use std::path::Path;
+
+// Community library imports
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use image::{Rgba, Rgba32FImage};
+
+// Crate imports
+use adaptri::cancellation::CancellationToken;
+use adaptri::color::ColorSpace;
+use adaptri::delaunay_of_random_image_pixels;
+use adaptri::interpolation::interpolate_rgba_in_triangle;
+use adaptri::locate::InsertionOrder;
+use adaptri::point::Point;
+use adaptri::progress::NoProgress;
+use adaptri::rasterization::{rasterize_mesh, Shading};
+use adaptri::refinement::{refine_mesh_by_centroid, ChangedFaces, RefinementCriteria};
+use adaptri::topology::is_point_in_triangle;
+use adaptri::Mesh;
+
+// Side lengths of the synthetic images
+const SIZES: [u32; 3] = [256, 1024, 2048];
+const N_INITIAL_POINTS: usize = 500;
+const N_ITERATIONS: usize = 3;
+
+// A color gradient with concentric rings, which has smooth regions as well
+// as sharp details to refine
+fn synthetic_image(size: u32) -> Rgba32FImage {
+    let center = size as f32 / 2.0;
+    Rgba32FImage::from_fn(size, size, |x, y| {
+        let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+        let ring = if (distance / 32.0).floor() % 2.0 == 0.0 { 1.0 } else { 0.2 };
+        Rgba([x as f32 / size as f32, y as f32 / size as f32, ring, 1.0])
+    })
+}
+
+// The synthetic images and the sample photo, named for the benchmark ids
+fn images() -> Vec<(String, Rgba32FImage)> {
+    let mut images: Vec<(String, Rgba32FImage)> =
+        SIZES.iter().map(|&size| (format!("synthetic_{}", size), synthetic_image(size))).collect();
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("data/2017_China_Chongqing_Boats.jpg");
+    let photo = image::open(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    images.push(("boats".to_string(), photo.to_rgba32f()));
+    images
+}
+
+fn seed(img: &Rgba32FImage) -> Mesh {
+    let (order, token) = (InsertionOrder::Hilbert, CancellationToken::new());
+    delaunay_of_random_image_pixels(img, N_INITIAL_POINTS, false, None, 4.0, 42, order, &NoProgress, &token)
+}
+
+fn criteria() -> RefinementCriteria<'static> {
+    RefinementCriteria {
+        max_diff: 15,
+        skip_transparent: false,
+        space: ColorSpace::Srgb,
+        palette: None,
+        palette_metric: ColorSpace::Oklab,
+        importance: None,
+        importance_strength: 4.0,
+        tolerance: None,
+    }
+}
+
+fn refine(mesh: &mut Mesh, img: &Rgba32FImage) -> usize {
+    let token = CancellationToken::new();
+    let mut changed = ChangedFaces::all();
+    refine_mesh_by_centroid(mesh, img, &criteria(), &mut changed, InsertionOrder::Hilbert, &NoProgress, &token)
+}
+
+// The mesh after the seeding and the given number of iterations
+fn refined_mesh(img: &Rgba32FImage, n_iterations: usize) -> Mesh {
+    let mut mesh = seed(img);
+    for _ in 0..n_iterations {
+        refine(&mut mesh, img);
+    }
+    mesh
+}
+
+fn bench_seeding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("seeding");
+    for (name, img) in images().iter() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), img, |b, img| b.iter(|| seed(img)));
+    }
+    group.finish();
+}
+
+// A single iteration checking every triangle of a mesh that was refined
+// before
+fn bench_refinement(c: &mut Criterion) {
+    let mut group = c.benchmark_group("refinement");
+    group.sample_size(10);
+    for (name, img) in images().iter() {
+        let mesh = refined_mesh(img, N_ITERATIONS - 1);
+        group.bench_with_input(BenchmarkId::from_parameter(name), img, |b, img| {
+            b.iter_batched(|| mesh.clone(), |mut mesh| refine(&mut mesh, img), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn bench_rasterization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rasterization");
+    group.sample_size(10);
+    let token = CancellationToken::new();
+    for (name, img) in images().iter() {
+        let mesh = refined_mesh(img, N_ITERATIONS);
+        let (width, height) = (img.width() as i32, img.height() as i32);
+        for shading in [Shading::Flat, Shading::Gouraud] {
+            let id = BenchmarkId::new(format!("{:?}", shading), name);
+            group.bench_with_input(id, &mesh, |b, mesh| {
+                b.iter(|| rasterize_mesh(mesh, width, height, shading, ColorSpace::Srgb, None, &NoProgress, &token))
+            });
+        }
+    }
+    group.finish();
+}
+
+// The points of a 100x100 grid around a triangle, about half of them inside
+fn triangle_and_points() -> (Mesh, Vec<Point>) {
+    let c = Rgba([0.2, 0.4, 0.6, 1.0]);
+    let mut mesh = Mesh::new();
+    mesh.insert(Point::new(0.0, 0.0, c));
+    mesh.insert(Point::new(100.0, 0.0, Rgba([0.8, 0.1, 0.3, 1.0])));
+    mesh.insert(Point::new(0.0, 100.0, Rgba([0.1, 0.9, 0.2, 0.5])));
+    let points = (0..100).flat_map(|x| (0..100).map(move |y| Point::new(x as f32, y as f32, c))).collect();
+    (mesh, points)
+}
+
+fn bench_point_in_triangle(c: &mut Criterion) {
+    let (mesh, points) = triangle_and_points();
+    let triangle = mesh.triangles().next().unwrap().as_triangle();
+    c.bench_function("is_point_in_triangle", |b| {
+        b.iter(|| points.iter().filter(|point| is_point_in_triangle(black_box(point), &triangle)).count())
+    });
+}
+
+fn bench_interpolation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("barycentric_interpolation");
+    let (mesh, points) = triangle_and_points();
+    let triangle = mesh.triangles().next().unwrap().as_triangle();
+    for space in [ColorSpace::Srgb, ColorSpace::Linear, ColorSpace::Oklab] {
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{:?}", space)), &space, |b, &space| {
+            b.iter(|| {
+                for point in points.iter() {
+                    black_box(interpolate_rgba_in_triangle(black_box(point), &triangle, space));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_seeding,
+    bench_refinement,
+    bench_rasterization,
+    bench_point_in_triangle,
+    bench_interpolation
+);
+criterion_main!(benches);
@@ -0,0 +1,133 @@
+// Standard library imports
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Community library imports
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+// Timing of an animation in milliseconds. The last frame is shown for
+// `hold` instead of `frame_delay` so that the final result stays visible.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AnimationTiming {
+    pub frame_delay: u16,
+    pub hold: u16,
+}
+
+// Saves the frames as a looping animation. The format is chosen by the file
+// extension, `.gif` for an animated GIF and `.png` or `.apng` for an APNG.
+pub fn save_animation(frames: &[RgbaImage], timing: AnimationTiming, path: &Path) -> io::Result<()> {
+    if frames.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "no frames to animate"));
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gif") => write_gif(frames, timing, &mut writer)?,
+        Some("png") | Some("apng") => write_apng(frames, timing, &mut writer)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unsupported animation format of {}", path.display()),
+            ))
+        }
+    }
+    writer.flush()
+}
+
+fn frame_delay(i: usize, n_frames: usize, timing: AnimationTiming) -> u16 {
+    if i + 1 == n_frames {
+        timing.hold
+    } else {
+        timing.frame_delay
+    }
+}
+
+pub fn write_gif<W: Write>(frames: &[RgbaImage], timing: AnimationTiming, w: &mut W) -> io::Result<()> {
+    let mut encoder = GifEncoder::new(w);
+    encoder.set_repeat(Repeat::Infinite).map_err(to_io_error)?;
+
+    for (i, frame) in frames.iter().enumerate() {
+        let delay = Delay::from_numer_denom_ms(frame_delay(i, frames.len(), timing) as u32, 1);
+        encoder
+            .encode_frame(Frame::from_parts(frame.clone(), 0, 0, delay))
+            .map_err(to_io_error)?;
+    }
+    Ok(())
+}
+
+pub fn write_apng<W: Write>(frames: &[RgbaImage], timing: AnimationTiming, w: &mut W) -> io::Result<()> {
+    let (width, height) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+
+    let mut writer = encoder.write_header()?;
+    for (i, frame) in frames.iter().enumerate() {
+        writer.set_frame_delay(frame_delay(i, frames.len(), timing), 1000)?;
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+fn to_io_error(err: image::ImageError) -> io::Error {
+    io::Error::other(err)
+}
+
+#[cfg(test)]
+mod animation_tests {
+    use image::codecs::gif::GifDecoder;
+    use image::{AnimationDecoder, Rgba};
+    use super::*;
+
+    const TIMING: AnimationTiming = AnimationTiming { frame_delay: 100, hold: 1000 };
+
+    fn create_frames() -> Vec<RgbaImage> {
+        vec![
+            RgbaImage::from_pixel(4, 3, Rgba([255, 0, 0, 255])),
+            RgbaImage::from_pixel(4, 3, Rgba([0, 255, 0, 255])),
+            RgbaImage::from_pixel(4, 3, Rgba([0, 0, 255, 255])),
+        ]
+    }
+
+    #[test]
+    fn test_frame_delay() {
+        assert_eq!(frame_delay(0, 3, TIMING), 100);
+        assert_eq!(frame_delay(1, 3, TIMING), 100);
+        assert_eq!(frame_delay(2, 3, TIMING), 1000);
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let mut buffer = Vec::new();
+        write_gif(&create_frames(), TIMING, &mut buffer).unwrap();
+
+        let decoder = GifDecoder::new(buffer.as_slice()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+        assert_eq!(frames[2].delay().numer_denom_ms(), (1000, 1));
+        assert_eq!(*frames[1].buffer().get_pixel(0, 0), Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_write_apng() {
+        let mut buffer = Vec::new();
+        write_apng(&create_frames(), TIMING, &mut buffer).unwrap();
+
+        let reader = png::Decoder::new(buffer.as_slice()).read_info().unwrap();
+        let animation_control = reader.info().animation_control.unwrap();
+        assert_eq!(animation_control.num_frames, 3);
+        assert_eq!(animation_control.num_plays, 0);
+    }
+
+    #[test]
+    fn test_save_without_frames() {
+        let result = save_animation(&[], TIMING, Path::new("refinement.gif"));
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,226 @@
+// Standard library imports
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Community library imports
+use image::ImageFormat;
+
+// An image to process together with its path relative to the directory it
+// was found in, so that the directory structure can be kept in the output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+}
+
+impl Input {
+    // An image given as a file, which is its own relative path.
+    pub fn file(path: PathBuf) -> Input {
+        let relative_path = PathBuf::from(path.file_name().unwrap_or_default());
+        Input { path, relative_path }
+    }
+}
+
+// Expands the given files, directories and glob patterns into the images to
+// process, in the given order and without duplicates.
+pub fn collect_inputs(patterns: &[String], recursive: bool) -> Result<Vec<Input>, String> {
+    let mut inputs = Vec::new();
+    for pattern in patterns {
+        let path = PathBuf::from(pattern);
+        if path.is_dir() {
+            collect_directory(&path, &path, recursive, &mut inputs)?;
+        } else if pattern.contains(['*', '?', '[']) {
+            let paths = glob::glob(pattern).map_err(|err| format!("invalid pattern '{}': {}", pattern, err))?;
+            let n_inputs = inputs.len();
+            for path in paths {
+                let path = path.map_err(|err| err.to_string())?;
+                if path.is_file() {
+                    inputs.push(Input::file(path));
+                }
+            }
+            if inputs.len() == n_inputs {
+                return Err(format!("no files match '{}'", pattern));
+            }
+        } else {
+            inputs.push(Input::file(path));
+        }
+    }
+
+    let mut unique: Vec<Input> = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        if !unique.iter().any(|other| other.path == input.path) {
+            unique.push(input);
+        }
+    }
+
+    Ok(unique)
+}
+
+// Adds the images in the directory in alphabetical order. Images written by
+// an earlier run are skipped, so that running twice does not triangulize
+// them again.
+fn collect_directory(root: &Path, dir: &Path, recursive: bool, inputs: &mut Vec<Input>) -> Result<(), String> {
+    let mut paths = fs::read_dir(dir)
+        .map_err(|err| format!("{}: {}", dir.display(), err))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()
+        .map_err(|err| format!("{}: {}", dir.display(), err))?;
+    paths.sort();
+
+    for path in paths {
+        if path.is_dir() {
+            if recursive {
+                collect_directory(root, &path, recursive, inputs)?;
+            }
+        } else if ImageFormat::from_path(&path).is_ok() && !is_output(&path) {
+            let relative_path = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+            inputs.push(Input { path, relative_path });
+        }
+    }
+    Ok(())
+}
+
+fn is_output(path: &Path) -> bool {
+    path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| stem.ends_with("_triangulized"))
+}
+
+// Path of the triangulized image, next to the input or at the relative
+// path of the input within the output directory.
+pub fn output_path(input: &Input, dir_out: Option<&Path>) -> PathBuf {
+    let stem = input.path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("image");
+    let extension = input.path.extension().and_then(|extension| extension.to_str()).unwrap_or("png");
+    let file_name = format!("{}_triangulized.{}", stem, extension);
+    match dir_out {
+        Some(dir) => dir.join(&input.relative_path).with_file_name(file_name),
+        None => input.path.with_file_name(file_name),
+    }
+}
+
+// Tells apart the files written for several inputs by appending the relative
+// path of the input to the file name, e.g. `report_boats.json`
+pub fn path_for_input(path: &Path, input: &Input) -> PathBuf {
+    let name = input_name(input);
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => path.with_file_name(format!("{}_{}.{}", stem, name, extension)),
+        None => path.with_file_name(format!("{}_{}", stem, name)),
+    }
+}
+
+fn input_name(input: &Input) -> String {
+    input
+        .relative_path
+        .with_extension("")
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+// The error of every input whose output image, or whose files written per
+// input if there are any, would overwrite those of an earlier input. Inputs
+// of the same name in different directories, e.g. a/x.jpg and b/x.png, only
+// collide if their outputs are collected in one directory or if files are
+// written per input.
+pub fn find_collisions(inputs: &[Input], dir_out: Option<&Path>, files_per_input: bool) -> Vec<Option<String>> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            inputs[..i]
+                .iter()
+                .find(|other| {
+                    output_path(other, dir_out) == output_path(input, dir_out)
+                        || (files_per_input && input_name(other) == input_name(input))
+                })
+                .map(|other| format!("its outputs would overwrite those of {}, rename one of them", other.path.display()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+
+    // Directory with two images, one of them in a subdirectory, an earlier
+    // output and a file that is no image
+    fn create_directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        for file in ["b.png", "a_triangulized.png", "notes.txt", "sub/c.jpg"] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_collect_directory() {
+        let dir = create_directory("adaptri_batch_test_directory");
+        let pattern = dir.to_str().unwrap().to_string();
+
+        let inputs = collect_inputs(std::slice::from_ref(&pattern), false).unwrap();
+        assert_eq!(inputs, vec![Input { path: dir.join("b.png"), relative_path: PathBuf::from("b.png") }]);
+
+        let inputs = collect_inputs(&[pattern], true).unwrap();
+        let relative_paths: Vec<&Path> = inputs.iter().map(|input| input.relative_path.as_path()).collect();
+        assert_eq!(relative_paths, vec![Path::new("b.png"), Path::new("sub/c.jpg")]);
+    }
+
+    #[test]
+    fn test_collect_glob() {
+        let dir = create_directory("adaptri_batch_test_glob");
+        let pattern = format!("{}/*.png", dir.to_str().unwrap());
+        let file = dir.join("b.png").to_str().unwrap().to_string();
+
+        // Globs match outputs as well, files given twice are processed once
+        let inputs = collect_inputs(&[pattern, file.clone()], false).unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(inputs[0].path, dir.join("a_triangulized.png"));
+
+        let pattern = format!("{}/*.gif", dir.to_str().unwrap());
+        assert!(collect_inputs(&[pattern], false).is_err());
+
+        // Images of the same name in different directories are kept
+        let other = dir.join("sub/b.png").to_str().unwrap().to_string();
+        fs::write(&other, b"").unwrap();
+        assert_eq!(collect_inputs(&[file, other], false).unwrap().len(), 2);
+
+        // Missing files are kept to fail on their own
+        let inputs = collect_inputs(&["missing.png".to_string()], false).unwrap();
+        assert_eq!(inputs, vec![Input::file(PathBuf::from("missing.png"))]);
+    }
+
+    #[test]
+    fn test_find_collisions() {
+        let inputs = [
+            Input::file(PathBuf::from("a/x.jpg")),
+            Input::file(PathBuf::from("b/x.jpg")),
+            Input::file(PathBuf::from("a/x.png")),
+            Input::file(PathBuf::from("a/y.png")),
+        ];
+
+        // Outputs next to the inputs only collide in the files per input
+        assert_eq!(find_collisions(&inputs, None, false), vec![None; 4]);
+        let collisions = find_collisions(&inputs, None, true);
+        assert_eq!(collisions[0], None);
+        assert_eq!(collisions[1], Some("its outputs would overwrite those of a/x.jpg, rename one of them".to_string()));
+        assert!(collisions[2].is_some());
+        assert_eq!(collisions[3], None);
+
+        // Outputs collected in one directory collide in the output image
+        let collisions = find_collisions(&inputs, Some(Path::new("out")), false);
+        assert_eq!(collisions.iter().filter(|collision| collision.is_some()).count(), 1);
+        assert!(collisions[1].is_some());
+    }
+
+    #[test]
+    fn test_output_paths() {
+        let input = Input { path: PathBuf::from("photos/sub/c.jpg"), relative_path: PathBuf::from("sub/c.jpg") };
+
+        assert_eq!(output_path(&input, None), PathBuf::from("photos/sub/c_triangulized.jpg"));
+        assert_eq!(output_path(&input, Some(Path::new("out"))), PathBuf::from("out/sub/c_triangulized.jpg"));
+        assert_eq!(path_for_input(Path::new("reports/run.json"), &input), PathBuf::from("reports/run_sub_c.json"));
+        assert_eq!(path_for_input(Path::new("mesh"), &input), PathBuf::from("mesh_sub_c"));
+    }
+}
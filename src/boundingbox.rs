@@ -43,33 +43,9 @@ impl BoundingBox {
 
 #[cfg(test)]
 mod boundingbox_tests {
-    use image::Rgba;
-    use spade::delaunay::{DelaunayTriangulation, FloatDelaunayTriangulation, DelaunayWalkLocate};
-    use spade::kernels::FloatKernel;
+    use crate::test_util::create_simple_triangulation;
     use super::*;
 
-    type Mesh = DelaunayTriangulation<Point, FloatKernel, DelaunayWalkLocate>;
-
-    fn create_simple_triangulation() -> Mesh {
-        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
-        delaunay.insert(Point {
-            x: 0.0,
-            y: 0.0,
-            c: Rgba([255, 0, 0, 255]),
-        });
-        delaunay.insert(Point {
-            x: 10.0,
-            y: 0.0,
-            c: Rgba([0, 255, 0, 255]),
-        });
-        delaunay.insert(Point {
-            x: 0.0,
-            y: 10.0,
-            c: Rgba([0, 0, 255, 255]),
-        });
-        delaunay
-    }
-
     #[test]
     fn test_creation() {
         let (xmin, xmax, ymin, ymax) = (0.0, 1.0, 2.0, 3.0);
@@ -0,0 +1,71 @@
+// Standard library imports
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Stops a running pipeline, when cancelled or when its time budget expired.
+// Clones share whether the token was cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    // A token that is cancelled together with this one and expires when
+    // the given time from now has passed.
+    pub fn with_budget(&self, budget: Duration) -> CancellationToken {
+        CancellationToken { cancelled: self.cancelled.clone(), deadline: Some(Instant::now() + budget) }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    // Whether the token was cancelled, which stops the pipeline with an
+    // error.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // Whether the time budget expired, which stops the refinement but
+    // finishes the image with the mesh refined so far.
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    // Whether loops that leave a valid mesh when stopped early, i.e. the
+    // seeding and the refinement, should stop.
+    pub fn should_stop(&self) -> bool {
+        self.is_cancelled() || self.is_expired()
+    }
+}
+
+#[cfg(test)]
+mod cancellation_tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel() {
+        let token = CancellationToken::new();
+        let budgeted = token.with_budget(Duration::from_secs(3600));
+        assert!(!budgeted.should_stop());
+
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(budgeted.is_cancelled());
+        assert!(!budgeted.is_expired());
+    }
+
+    #[test]
+    fn test_budget() {
+        let token = CancellationToken::new().with_budget(Duration::ZERO);
+        assert!(token.is_expired());
+        assert!(token.should_stop());
+        assert!(!token.is_cancelled());
+    }
+}
@@ -0,0 +1,877 @@
+// Standard library imports
+use std::env;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Community library imports
+use image::ColorType;
+use clap::{Arg, App, AppSettings, ArgMatches, SubCommand};
+use indicatif::MultiProgress;
+use log::{error, info, LevelFilter};
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+
+// Crate imports
+use crate::animation::AnimationTiming;
+use crate::batch::{collect_inputs, find_collisions};
+use crate::cancellation::CancellationToken;
+use crate::color::{parse_hex_color, ColorSpace};
+use crate::config::{Command, Config, ExportConfig, InspectConfig, OutputSize, RenderConfig, RenderSettings};
+use crate::export::{export_mesh, write_svg};
+use crate::filter::Filter;
+use crate::importance::Region;
+use crate::interpolation::interpolate_triangle_average_color;
+use crate::io::save_image_with_depth_of;
+use crate::locate::{InsertionOrder, LocateStrategy};
+use crate::palette::QuantizationMethod;
+use crate::progress::{init_terminal, Observer, ProgressBars, Stage};
+use crate::pyramid::scale_mesh;
+use crate::rasterization::Shading;
+use crate::report::{Histogram, MeshStatistics};
+use crate::settings::{Settings, PRESETS};
+use crate::wireframe::{WireframeMode, WireframeStyle};
+use crate::{
+    build_extrusion, build_palette, load_saved_mesh, process_image, quantize, render_mesh, visible_face_colors, Mesh,
+    Summary,
+};
+
+// Starting point of command line program
+pub fn run() {
+    let (command, bars) = parse_arguments();
+    let result = match command {
+        Command::Triangulate(config) => {
+            triangulate(&config, &bars);
+            Ok(())
+        }
+        Command::Render(config) => render(&config, &bars),
+        Command::Export(config) => export(&config),
+        Command::Inspect(config) => inspect(&config),
+    };
+    result.unwrap_or_else(|err| exit_with_error(err));
+}
+
+// Triangulizes all input images, exiting with an error if any of them failed
+fn triangulate(config: &Config, bars: &MultiProgress) {
+    // Step 0: Collecting the images
+    let inputs = collect_inputs(&config.inputs, config.recursive).unwrap_or_else(|err| exit_with_error(err));
+    if inputs.is_empty() {
+        exit_with_error("No images found");
+    }
+    if inputs.len() > 1 && config.path_mesh_in.is_some() {
+        exit_with_error("A mesh can only be resumed for a single input image");
+    }
+
+    // The images are processed in parallel, a failing image does not stop
+    // the others
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(config.n_jobs.unwrap_or(0))
+        .build()
+        .unwrap_or_else(|err| exit_with_error(err));
+    let batch = inputs.len() > 1;
+    let files_per_input = batch
+        && (config.path_report.is_some()
+            || config.path_mesh_out.is_some()
+            || !config.paths_export.is_empty()
+            || config.path_animation.is_some()
+            || config.path_error_map.is_some());
+    let collisions = find_collisions(&inputs, config.dir_out.as_deref(), files_per_input);
+    let token = CancellationToken::new();
+    let results: Vec<Result<Summary, String>> = pool.install(|| {
+        inputs
+            .par_iter()
+            .zip(collisions.par_iter())
+            .map(|(input, collision)| match collision {
+                Some(err) => Err(err.clone()),
+                None => {
+                    let observer = ProgressBars::new(bars, &input.relative_path.display().to_string());
+                    process_image(input, batch, config, &observer, &token)
+                }
+            })
+            .collect()
+    });
+
+    info!("\nSummary:");
+    let mut n_failed = 0;
+    for (input, result) in inputs.iter().zip(results.iter()) {
+        match result {
+            Ok(summary) => {
+                info!("- {} -> {}", input.path.display(), summary.path_out.display());
+                info!(
+                    "  {} vertices, MSE {:.6}, PSNR {:.2} dB, SSIM {:.4}, MS-SSIM {:.4}, {:.2} s{}",
+                    summary.n_vertices,
+                    summary.metrics.mse,
+                    summary.metrics.psnr,
+                    summary.metrics.ssim,
+                    summary.metrics.ms_ssim,
+                    summary.seconds,
+                    if summary.budget_expired { " (time budget expired)" } else { "" }
+                );
+            }
+            Err(err) => {
+                n_failed += 1;
+                error!("{}: {}", input.path.display(), err);
+            }
+        }
+    }
+    if n_failed > 0 {
+        eprintln!("ERROR: {} of {} images failed.", n_failed, inputs.len());
+        std::process::exit(1);
+    }
+}
+
+// Renders a saved mesh at any size, as a raster image or as an .svg file of
+// flat colored triangles
+fn render(config: &RenderConfig, bars: &MultiProgress) -> Result<(), String> {
+    let settings = &config.render;
+    let (mesh_file, mesh): (_, Mesh) = load_saved_mesh(&config.path_mesh)?;
+    let (width, height) = config.size.resolve(mesh_file.width, mesh_file.height);
+    let mesh = scale_mesh(&mesh, mesh_file.width, mesh_file.height, width, height);
+
+    let palette = match &settings.palette {
+        Some(spec) => Some(build_palette(spec, &visible_face_colors(&mesh, settings), settings)?),
+        None => None,
+    };
+    let face_colors = quantize(&mesh, palette.as_ref(), settings);
+
+    if config.path_out.extension().and_then(|ext| ext.to_str()) == Some("svg") {
+        if settings.shading != Shading::Flat || settings.wireframe.is_some() {
+            return Err("An .svg file can only be rendered with flat shading and without wireframe".to_string());
+        }
+        let face_colors = face_colors.unwrap_or_else(|| {
+            mesh.triangles()
+                .map(|face| interpolate_triangle_average_color(&face.as_triangle(), settings.color_space))
+                .collect()
+        });
+        let file = File::create(&config.path_out).map_err(|err| format!("{}: {}", config.path_out.display(), err))?;
+        write_svg(&mesh, width, height, &face_colors, &mut BufWriter::new(file)).map_err(|err| err.to_string())?;
+    } else {
+        let observer = ProgressBars::new(bars, &config.path_mesh.display().to_string());
+        observer.stage_started(Stage::Rasterization, mesh.num_triangles());
+        let token = CancellationToken::new();
+        let img = render_mesh(&mesh, width, height, settings, face_colors.as_deref(), &observer, &token);
+        observer.stage_finished();
+        save_image_with_depth_of(img, ColorType::Rgba8, &config.path_out).map_err(|err| err.to_string())?;
+    }
+    info!("\nRendered {}x{} pixels to {}", width, height, config.path_out.display());
+    Ok(())
+}
+
+// Exports a saved mesh to scientific mesh, vector and 3D formats
+fn export(config: &ExportConfig) -> Result<(), String> {
+    let (mesh_file, mesh): (_, Mesh) = load_saved_mesh(&config.path_mesh)?;
+    let extrusion = build_extrusion(config.height.as_deref(), config.height_scale)?;
+    for path in config.paths_export.iter() {
+        export_mesh(&mesh, path, extrusion.as_ref(), mesh_file.parameters.color_space).map_err(|err| err.to_string())?;
+        info!("Exported {}", path.display());
+    }
+    Ok(())
+}
+
+// Prints the size, parameters and statistics of a saved mesh
+fn inspect(config: &InspectConfig) -> Result<(), String> {
+    let (mesh_file, mesh): (_, Mesh) = load_saved_mesh(&config.path_mesh)?;
+    let statistics = MeshStatistics::of(&mesh);
+
+    if config.json {
+        let inspection = serde_json::json!({
+            "version": mesh_file.version,
+            "width": mesh_file.width,
+            "height": mesh_file.height,
+            "parameters": mesh_file.parameters,
+            "mesh": statistics,
+        });
+        println!("{}", serde_json::to_string_pretty(&inspection).map_err(|err| err.to_string())?);
+        return Ok(());
+    }
+
+    let parameters = &mesh_file.parameters;
+    println!("Mesh file:        {} (version {})", config.path_mesh.display(), mesh_file.version);
+    println!("Image size:       {}x{}", mesh_file.width, mesh_file.height);
+    println!("Initial nodes:    {}", parameters.n_initial_points);
+    println!("Iterations:       {}", parameters.n_iterations);
+    println!("Max. difference:  {}", parameters.max_diff);
+    println!("Shading:          {:?}", parameters.shading);
+    println!("Color space:      {:?}", parameters.color_space);
+    println!("Vertices:         {}", statistics.n_vertices);
+    println!("Triangles:        {}", statistics.n_triangles);
+    println!("\nTriangle areas in pixels:");
+    print_histogram(&statistics.areas);
+    println!("\nTriangle angles in degrees:");
+    print_histogram(&statistics.angles);
+    Ok(())
+}
+
+// Prints every bin of the histogram as a row with a bar relative to the
+// fullest bin
+fn print_histogram(histogram: &Histogram) {
+    let largest = histogram.counts.iter().cloned().max().unwrap_or(0).max(1);
+    for (i, (bound, count)) in histogram.lower_bounds.iter().zip(histogram.counts.iter()).enumerate() {
+        let range = match histogram.lower_bounds.get(i + 1) {
+            Some(next) => format!("{} - {}", bound, next),
+            None => format!(">= {}", bound),
+        };
+        println!("  {:>14} {:>8} {}", range, count, "#".repeat(count * 40 / largest));
+    }
+}
+
+// Parses an option that clap does not validate, as it may come from a file
+fn parse_or_exit<T: FromStr<Err = String>>(value: &str) -> T {
+    value.parse::<T>().unwrap_or_else(|err| exit_with_error(err))
+}
+
+// Parses an option that may come from a file, exiting with an error that
+// names the option if its value is invalid
+fn parse_setting<T: FromStr>(settings: &Settings, name: &str) -> Option<T> {
+    settings.parse::<T>(name).unwrap_or_else(|err| exit_with_error(err))
+}
+
+fn exit_with_error<E: Display>(err: E) -> ! {
+    eprintln!("ERROR: {}.", err);
+    eprintln!("Exiting program.");
+    std::process::exit(1);
+}
+
+// Parses the subcommand and its options and sets up the logging and the
+// progress bars for the verbosity. Without a subcommand, the options are
+// those of `triangulate`, as before there were subcommands.
+fn parse_arguments() -> (Command, MultiProgress) {
+    let mut args: Vec<OsString> = env::args_os().collect();
+    let first = args.iter().skip(1).filter_map(|arg| arg.to_str()).find(|arg| !is_verbosity_flag(arg));
+    if let Some(first) = first {
+        if first.starts_with('-') && !["-h", "--help", "-V", "--version"].contains(&first) {
+            args.insert(1, OsString::from("triangulate"));
+        }
+    }
+
+    let matches = App::new("trimage")
+        .version("1.0")
+        .author("Paz Vi <paz@twowaysix.com>")
+        .about("AdapTri - Adaptive Triangulizer")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .help("Prints more details, e.g. the points inserted by every iteration and the time of every stage. -vv prints everything.")
+            .multiple(true)
+            .global(true))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .help("Prints only warnings and errors, without progress bars.")
+            .conflicts_with("verbose")
+            .global(true))
+        .subcommand(SubCommand::with_name("triangulate")
+            .about("Triangulizes images and saves the mesh, the output image and further files.")
+            .arg(Arg::with_name("file")
+                .short("f")
+                .long("file")
+                .help("The input images, directories of images or glob patterns like 'photos/*.jpg'.")
+                .takes_value(true)
+                .multiple(true))
+            .arg(Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .help("Reads the options from a .toml file with the option names as keys. Options given on the command line take precedence.")
+                .takes_value(true))
+            .arg(Arg::with_name("preset")
+                .long("preset")
+                .help("Starts from a built-in set of options, which the configuration file and the command line override.")
+                .takes_value(true)
+                .possible_values(&PRESETS.map(|(name, _)| name)))
+            .arg(Arg::with_name("recursive")
+                .short("r")
+                .long("recursive")
+                .help("Searches the subdirectories of input directories as well."))
+            .arg(Arg::with_name("no_recursive")
+                .long("no-recursive")
+                .help("Turns off --recursive, e.g. when the configuration turns it on.")
+                .overrides_with("recursive"))
+            .arg(Arg::with_name("out_dir")
+                .short("o")
+                .long("out-dir")
+                .help("Saves the output images to this directory instead of next to the inputs.")
+                .takes_value(true))
+            .arg(Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .help("Number of images processed in parallel. All cores are used if not given.")
+                .takes_value(true))
+            .arg(Arg::with_name("time_budget")
+                .long("time-budget")
+                .help("Stops refining an image after the given number of seconds and finishes it with the mesh refined so far.")
+                .takes_value(true))
+            .arg(Arg::with_name("locate")
+                .long("locate")
+                .help("How new points are located in the mesh: by walking from the last inserted point or by an r-tree.")
+                .takes_value(true)
+                .possible_values(&["walk", "tree"])
+                .default_value("walk"))
+            .arg(Arg::with_name("insertion_order")
+                .long("insertion-order")
+                .help("Order in which the initial nodes and the points of every iteration are inserted.")
+                .takes_value(true)
+                .possible_values(&["hilbert", "morton", "unsorted"])
+                .default_value("hilbert"))
+            .arg(Arg::with_name("points")
+                .short("p")
+                .long("points")
+                .help("Number of initial mesh nodes.")
+                .takes_value(true)
+                .default_value("100"))
+            .arg(Arg::with_name("seed")
+                .long("seed")
+                .help("Seed of the random placement of the initial mesh nodes. Random if not given.")
+                .takes_value(true))
+            .arg(Arg::with_name("iterations")
+                .short("i")
+                .long("iterations")
+                .help("Number of iterations.")
+                .takes_value(true)
+                .default_value("4"))
+            .arg(Arg::with_name("max_diff")
+                .short("d")
+                .long("max_diff")
+                .help("Maximum difference for no refinement.")
+                .takes_value(true)
+                .default_value("15"))
+            .arg(Arg::with_name("skip_transparent")
+                .long("skip-transparent")
+                .help("Neither seeds nor refines in fully transparent regions."))
+            .arg(Arg::with_name("no_skip_transparent")
+                .long("no-skip-transparent")
+                .help("Turns off --skip-transparent, e.g. when the configuration turns it on.")
+                .overrides_with("skip_transparent"))
+            .arg(Arg::with_name("prefilter")
+                .long("prefilter")
+                .help("Compares the mesh with a filtered image: gaussian[:sigma], bilateral[:sigma_spatial,sigma_range] or median[:radius].")
+                .takes_value(true))
+            .arg(Arg::with_name("sample_original")
+                .long("sample-original")
+                .help("Takes the vertex colors from the original image instead of the pre-filtered one. Requires --prefilter."))
+            .arg(Arg::with_name("no_sample_original")
+                .long("no-sample-original")
+                .help("Turns off --sample-original, e.g. when the configuration or preset turns it on.")
+                .overrides_with("sample_original"))
+            .arg(Arg::with_name("pyramid")
+                .long("pyramid")
+                .help("Seeds and refines at the coarsest of the given number of Gaussian pyramid levels first, then at every finer level. The iterations are split between the levels.")
+                .takes_value(true)
+                .default_value("1"))
+            .arg(Arg::with_name("adaptive")
+                .long("adaptive")
+                .help("Scales the max. difference by the local contrast of the image with the given strength, e.g. 0.5.")
+                .takes_value(true))
+            .arg(Arg::with_name("adaptive_radius")
+                .long("adaptive-radius")
+                .help("Radius in pixels of the window in which the local contrast is measured.")
+                .takes_value(true)
+                .default_value("3"))
+            .arg(Arg::with_name("roi_mask")
+                .long("roi-mask")
+                .help("Grayscale image of the regions of interest, which are refined in more detail. White is most important.")
+                .takes_value(true))
+            .arg(Arg::with_name("roi_rect")
+                .long("roi-rect")
+                .help("Rectangular region of interest as x,y,width,height in pixels. Can be given multiple times.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("roi_polygon")
+                .long("roi-polygon")
+                .help("Polygonal region of interest as x1,y1,x2,y2,x3,y3,... in pixels. Can be given multiple times.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("roi_strength")
+                .long("roi-strength")
+                .help("How many times the max. difference is raised and the node density lowered outside the regions of interest.")
+                .takes_value(true)
+                .default_value("4"))
+            .arg(Arg::with_name("export")
+                .short("e")
+                .long("export")
+                .help("Exports the mesh to a .msh, .vtk, .node/.ele/.poly, .svg, .obj or .glb file. Can be given multiple times.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1))
+            .arg(Arg::with_name("height")
+                .long("height")
+                .help("Extrudes the .obj and .glb exports in Z by 'luminance' or by a height map image file.")
+                .takes_value(true))
+            .arg(Arg::with_name("height_scale")
+                .long("height-scale")
+                .help("Height of the brightest point of the extruded exports in pixels.")
+                .takes_value(true)
+                .default_value("50"))
+            .arg(Arg::with_name("error_map")
+                .long("error-map")
+                .help("Saves the per-pixel error of the output as a heat map image.")
+                .takes_value(true))
+            .arg(Arg::with_name("animate")
+                .long("animate")
+                .help("Saves the refinement process as an animated .gif or .png (APNG).")
+                .takes_value(true))
+            .arg(Arg::with_name("frame_delay")
+                .long("frame-delay")
+                .help("Delay between the frames of the animation in milliseconds.")
+                .takes_value(true)
+                .default_value("500"))
+            .arg(Arg::with_name("hold")
+                .long("hold")
+                .help("How long the final frame of the animation is shown in milliseconds.")
+                .takes_value(true)
+                .default_value("3000"))
+            .args(&render_args())
+            .arg(Arg::with_name("refine_to_palette")
+                .long("refine-to-palette")
+                .help("Only refines where the palette color of a triangle is wrong. Generated palettes are taken from the initial nodes. Requires --palette."))
+            .arg(Arg::with_name("no_refine_to_palette")
+                .long("no-refine-to-palette")
+                .help("Turns off --refine-to-palette, e.g. when the configuration turns it on.")
+                .overrides_with("refine_to_palette"))
+            .arg(Arg::with_name("save_mesh")
+                .long("save-mesh")
+                .help("Saves the mesh to a .json file or to a compact binary file (any other extension).")
+                .takes_value(true))
+            .arg(Arg::with_name("report")
+                .long("report")
+                .help("Saves the parameters, seed, statistics, timings and quality of the run to a .json file.")
+                .takes_value(true))
+            .arg(Arg::with_name("load_mesh")
+                .long("load-mesh")
+                .help("Resumes from a mesh saved with --save-mesh instead of seeding a new one.")
+                .takes_value(true)))
+        .subcommand(SubCommand::with_name("render")
+            .about("Renders a saved mesh as an image of any size or as an .svg file.")
+            .arg(Arg::with_name("mesh")
+                .short("m")
+                .long("mesh")
+                .help("The mesh saved with --save-mesh.")
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::with_name("out")
+                .short("o")
+                .long("out")
+                .help("The output image. An .svg file is written as vector graphics.")
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::with_name("size")
+                .long("size")
+                .help("Size of the output as width, e.g. 1920, or as width and height, e.g. 1920x1080. The size of the original image if not given.")
+                .takes_value(true))
+            .arg(Arg::with_name("scale")
+                .long("scale")
+                .help("Scales the original image size by the given factor.")
+                .takes_value(true)
+                .conflicts_with("size"))
+            .args(&render_args()))
+        .subcommand(SubCommand::with_name("export")
+            .about("Exports a saved mesh to mesh, vector and 3D formats.")
+            .arg(Arg::with_name("mesh")
+                .short("m")
+                .long("mesh")
+                .help("The mesh saved with --save-mesh.")
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::with_name("export")
+                .short("e")
+                .long("export")
+                .help("Exports the mesh to a .msh, .vtk, .node/.ele/.poly, .svg, .obj or .glb file. Can be given multiple times.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true))
+            .arg(Arg::with_name("height")
+                .long("height")
+                .help("Extrudes the .obj and .glb exports in Z by 'luminance' or by a height map image file.")
+                .takes_value(true))
+            .arg(Arg::with_name("height_scale")
+                .long("height-scale")
+                .help("Height of the brightest point of the extruded exports in pixels.")
+                .takes_value(true)
+                .default_value("50")))
+        .subcommand(SubCommand::with_name("inspect")
+            .about("Prints the size, parameters and statistics of a saved mesh.")
+            .arg(Arg::with_name("mesh")
+                .short("m")
+                .long("mesh")
+                .help("The mesh saved with --save-mesh.")
+                .takes_value(true)
+                .required(true))
+            .arg(Arg::with_name("json")
+                .long("json")
+                .help("Prints the statistics as JSON.")))
+        .get_matches_from(args);
+
+    // Global options are passed on to the subcommand
+    let (name, matches) = matches.subcommand();
+    let matches = matches.unwrap();
+    let level = match (matches.is_present("quiet"), matches.occurrences_of("verbose")) {
+        (true, _) => LevelFilter::Warn,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    let bars = init_terminal(level);
+
+    let command = match name {
+        "triangulate" => Command::Triangulate(Box::new(parse_triangulate(matches))),
+        "render" => Command::Render(parse_render(matches)),
+        "export" => Command::Export(parse_export(matches)),
+        "inspect" => Command::Inspect(InspectConfig {
+            path_mesh: PathBuf::from(matches.value_of("mesh").unwrap()),
+            json: matches.is_present("json"),
+        }),
+        _ => unreachable!(),
+    };
+    (command, bars)
+}
+
+// Whether the argument is -v, -vv, ..., -q or one of their long forms
+fn is_verbosity_flag(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        Some("-verbose") | Some("-quiet") | Some("q") => true,
+        Some(flags) => !flags.is_empty() && flags.chars().all(|c| c == 'v'),
+        None => false,
+    }
+}
+
+// Options of how a mesh is turned into an image
+fn render_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("shading")
+            .short("s")
+            .long("shading")
+            .help("How the triangles are colored.")
+            .takes_value(true)
+            .possible_values(&["flat", "gouraud"])
+            .default_value("flat"),
+        Arg::with_name("color_space")
+            .long("color-space")
+            .help("Color space in which colors are averaged, interpolated and compared.")
+            .takes_value(true)
+            .possible_values(&["srgb", "linear", "lab", "oklab", "hsv"])
+            .default_value("srgb"),
+        Arg::with_name("palette")
+            .long("palette")
+            .help("Limits the triangle colors to a palette of the given number of colors or to the colors of a .gpl or hex list file.")
+            .takes_value(true),
+        Arg::with_name("palette_method")
+            .long("palette-method")
+            .help("How the palette is generated from the triangle colors.")
+            .takes_value(true)
+            .possible_values(&["kmeans", "median-cut"])
+            .default_value("kmeans"),
+        Arg::with_name("palette_metric")
+            .long("palette-metric")
+            .help("Color space in which the nearest palette color is searched.")
+            .takes_value(true)
+            .possible_values(&["srgb", "linear", "lab", "oklab", "hsv"])
+            .default_value("oklab"),
+        Arg::with_name("dither")
+            .long("dither")
+            .help("Diffuses the palette error of every triangle to its neighbors."),
+        Arg::with_name("no_dither")
+            .long("no-dither")
+            .help("Turns off --dither, e.g. when the configuration turns it on.")
+            .overrides_with("dither"),
+        Arg::with_name("wireframe")
+            .short("w")
+            .long("wireframe")
+            .help("Draws the triangle edges on top of the triangles or only the edges.")
+            .takes_value(true)
+            .possible_values(&["overlay", "only"]),
+        Arg::with_name("line_color")
+            .long("line-color")
+            .help("Color of the wireframe as hex rrggbb or rrggbbaa.")
+            .takes_value(true)
+            .default_value("000000"),
+        Arg::with_name("line_width")
+            .long("line-width")
+            .help("Width of the wireframe edges in pixels.")
+            .takes_value(true)
+            .default_value("1"),
+        Arg::with_name("line_opacity")
+            .long("line-opacity")
+            .help("Opacity of the wireframe between 0 and 1.")
+            .takes_value(true)
+            .default_value("1"),
+        Arg::with_name("vertex_radius")
+            .long("vertex-radius")
+            .help("Draws the vertices as dots of the given radius in pixels.")
+            .takes_value(true)
+            .default_value("0"),
+        Arg::with_name("no_anti_aliasing")
+            .long("no-anti-aliasing")
+            .help("Draws the wireframe without anti-aliasing."),
+        Arg::with_name("background")
+            .long("background")
+            .help("Background color as hex rrggbb or rrggbbaa when only the wireframe is drawn.")
+            .takes_value(true)
+            .default_value("ffffff"),
+    ]
+}
+
+// Options that are only used together with another option. They are checked
+// after the configuration and the preset are merged with the command line,
+// which clap does not know about.
+const REQUIREMENTS: [(&str, &str); 2] = [("sample_original", "prefilter"), ("refine_to_palette", "palette")];
+
+fn parse_triangulate(matches: &ArgMatches) -> Config {
+    // Options given on the command line override the configuration file,
+    // which overrides the preset
+    let path_config = matches.value_of("config").map(PathBuf::from);
+    let preset = matches.value_of("preset");
+    let settings = Settings::new(matches, path_config.as_deref(), preset).unwrap_or_else(|err| exit_with_error(err));
+    settings.check_requirements(&REQUIREMENTS).unwrap_or_else(|err| exit_with_error(err));
+
+    let inputs = settings.values_of("file").unwrap_or_default();
+    if inputs.is_empty() {
+        exit_with_error("No input images given, neither with -f/--file nor in the configuration");
+    }
+    let recursive = settings.is_present("recursive");
+    let dir_out = settings.value_of("out_dir").map(PathBuf::from);
+    let n_jobs = parse_setting::<usize>(&settings, "jobs");
+    if n_jobs == Some(0) {
+        exit_with_error("invalid value '0' for option 'jobs'");
+    }
+    let time_budget = settings.value_of("time_budget").map(|value| match value.parse::<f64>() {
+        Ok(seconds) if seconds >= 0.0 && seconds.is_finite() => Duration::from_secs_f64(seconds),
+        _ => exit_with_error(format!("invalid time budget '{}'", value)),
+    });
+    let locate = parse_or_exit::<LocateStrategy>(&settings.value_of("locate").unwrap());
+    let insertion_order = parse_or_exit::<InsertionOrder>(&settings.value_of("insertion_order").unwrap());
+
+    // TODO: Handle wrong input better
+    let n_initial_points = settings.value_of("points").unwrap().parse::<usize>().unwrap_or(100);
+    if n_initial_points < 4 {
+        exit_with_error(format!("At least 4 initial nodes are needed for the corners, not {}", n_initial_points));
+    }
+    let seed = match settings.value_of("seed") {
+        Some(value) => value
+            .parse::<u64>()
+            .unwrap_or_else(|_| exit_with_error(format!("invalid seed '{}'", value))),
+        None => rand::random(),
+    };
+    let n_iterations = parse_setting::<usize>(&settings, "iterations").unwrap();
+    let max_diff = settings.value_of("max_diff").unwrap().parse::<i32>().unwrap_or(15);
+    let skip_transparent = settings.is_present("skip_transparent");
+    let prefilter = settings.value_of("prefilter").map(|filter| parse_or_exit::<Filter>(&filter));
+    let sample_original = settings.is_present("sample_original");
+    let pyramid_levels = settings.value_of("pyramid").unwrap().parse::<usize>().unwrap_or(1).max(1);
+    let adaptive_strength = parse_setting::<f32>(&settings, "adaptive");
+    let adaptive_radius = settings.value_of("adaptive_radius").unwrap().parse::<u32>().unwrap_or(3);
+    let path_roi_mask = settings.value_of("roi_mask").map(PathBuf::from);
+    let mut roi_regions = Vec::new();
+    if let Some(values) = settings.values_of("roi_rect") {
+        for value in values {
+            roi_regions.push(Region::parse_rectangle(&value).unwrap_or_else(|err| exit_with_error(err)));
+        }
+    }
+    if let Some(values) = settings.values_of("roi_polygon") {
+        for value in values {
+            roi_regions.push(Region::parse_polygon(&value).unwrap_or_else(|err| exit_with_error(err)));
+        }
+    }
+    let roi_strength = settings.value_of("roi_strength").unwrap().parse::<f32>().unwrap_or(4.0);
+    let paths_export: Vec<PathBuf> = match settings.values_of("export") {
+        Some(values) => values.into_iter().map(PathBuf::from).collect(),
+        None => Vec::new(),
+    };
+    let height = settings.value_of("height");
+    let height_scale = settings.value_of("height_scale").unwrap().parse::<f32>().unwrap_or(50.0);
+    let render = parse_render_settings(&settings);
+    let refine_to_palette = settings.is_present("refine_to_palette");
+    let path_error_map = settings.value_of("error_map").map(PathBuf::from);
+    let path_animation = settings.value_of("animate").map(PathBuf::from);
+    let animation_timing = AnimationTiming {
+        frame_delay: settings.value_of("frame_delay").unwrap().parse::<u16>().unwrap_or(500),
+        hold: settings.value_of("hold").unwrap().parse::<u16>().unwrap_or(3000),
+    };
+    let path_mesh_out = settings.value_of("save_mesh").map(PathBuf::from);
+    let path_mesh_in = settings.value_of("load_mesh").map(PathBuf::from);
+    let path_report = settings.value_of("report").map(PathBuf::from);
+
+    // Every key of the configuration has to be an option
+    if let Some(key) = settings.unknown_keys().first() {
+        exit_with_error(format!("Unknown option '{}' in the configuration", key));
+    }
+
+    info!("\nChosen parameters:");
+    if let Some(path) = &path_config {
+        info!("- Configuration:    {}", path.to_str().unwrap());
+    }
+    if let Some(preset) = preset {
+        info!("- Preset:           {}", preset);
+    }
+    for input in inputs.iter() {
+        info!("- Input path:       {}{}", input, if recursive { " (recursive)" } else { "" });
+    }
+    if let Some(dir) = &dir_out {
+        info!("- Out directory:    {}", dir.to_str().unwrap());
+    }
+    if let Some(n_jobs) = n_jobs {
+        info!("- Parallel jobs:    {}", n_jobs);
+    }
+    if let Some(budget) = &time_budget {
+        info!("- Time budget:      {} s", budget.as_secs_f64());
+    }
+    if locate != LocateStrategy::Walk || insertion_order != InsertionOrder::Hilbert {
+        info!("- Point location:   {:?}, {:?} insertion order", locate, insertion_order);
+    }
+    info!("- Initial nodes:    {}", n_initial_points);
+    info!("- Seed:             {}", seed);
+    info!("- Iterations:       {}", n_iterations);
+    info!("- Max. difference:  {}", max_diff);
+    if skip_transparent {
+        info!("- Skipping fully transparent regions");
+    }
+    if let Some(filter) = &prefilter {
+        info!("- Pre-filter:       {:?}{}", filter, if sample_original { " (original colors)" } else { "" });
+    }
+    if pyramid_levels > 1 {
+        info!("- Pyramid levels:   {}", pyramid_levels);
+    }
+    if let Some(strength) = adaptive_strength {
+        info!("- Adaptive:         strength {} (radius {})", strength, adaptive_radius);
+    }
+    if let Some(path) = &path_roi_mask {
+        info!("- ROI mask:         {}", path.to_str().unwrap());
+    }
+    for region in roi_regions.iter() {
+        info!("- ROI:              {:?}", region);
+    }
+    if path_roi_mask.is_some() || !roi_regions.is_empty() {
+        info!("- ROI strength:     {}", roi_strength);
+    }
+    print_render_settings(&render);
+    if refine_to_palette {
+        info!("- Refining towards the palette");
+    }
+    for path in paths_export.iter() {
+        info!("- Export path:      {}", path.to_str().unwrap());
+    }
+    if let Some(height) = &height {
+        info!("- Height:           {} (scale {})", height, height_scale);
+    }
+    if let Some(path) = &path_error_map {
+        info!("- Error map path:   {}", path.to_str().unwrap());
+    }
+    if let Some(path) = &path_animation {
+        info!("- Animation path:   {}", path.to_str().unwrap());
+    }
+    if let Some(path) = &path_mesh_in {
+        info!("- Mesh input path:  {}", path.to_str().unwrap());
+    }
+    if let Some(path) = &path_mesh_out {
+        info!("- Mesh output path: {}", path.to_str().unwrap());
+    }
+    if let Some(path) = &path_report {
+        info!("- Report path:      {}", path.to_str().unwrap());
+    }
+
+    Config {
+        inputs,
+        recursive,
+        dir_out,
+        n_jobs,
+        time_budget,
+        locate,
+        insertion_order,
+        n_initial_points,
+        seed,
+        n_iterations,
+        max_diff,
+        skip_transparent,
+        prefilter,
+        sample_original,
+        pyramid_levels,
+        adaptive_strength,
+        adaptive_radius,
+        path_roi_mask,
+        roi_regions,
+        roi_strength,
+        paths_export,
+        height,
+        height_scale,
+        render,
+        refine_to_palette,
+        path_error_map,
+        path_animation,
+        animation_timing,
+        path_mesh_in,
+        path_mesh_out,
+        path_report,
+    }
+}
+
+fn parse_render(matches: &ArgMatches) -> RenderConfig {
+    let settings = Settings::new(matches, None, None).unwrap_or_else(|err| exit_with_error(err));
+    let path_mesh = PathBuf::from(matches.value_of("mesh").unwrap());
+    let path_out = PathBuf::from(matches.value_of("out").unwrap());
+    let size = match (matches.value_of("size"), matches.value_of("scale")) {
+        (Some(size), _) => parse_or_exit::<OutputSize>(size),
+        (None, Some(scale)) => match scale.parse::<f32>() {
+            Ok(scale) if scale > 0.0 => OutputSize::Scale(scale),
+            _ => exit_with_error(format!("invalid scale '{}'", scale)),
+        },
+        (None, None) => OutputSize::Original,
+    };
+    let render = parse_render_settings(&settings);
+
+    info!("\nChosen parameters:");
+    info!("- Mesh path:        {}", path_mesh.to_str().unwrap());
+    info!("- Output path:      {}", path_out.to_str().unwrap());
+    if size != OutputSize::Original {
+        info!("- Size:             {:?}", size);
+    }
+    print_render_settings(&render);
+
+    RenderConfig { path_mesh, path_out, size, render }
+}
+
+fn parse_export(matches: &ArgMatches) -> ExportConfig {
+    ExportConfig {
+        path_mesh: PathBuf::from(matches.value_of("mesh").unwrap()),
+        paths_export: matches.values_of("export").unwrap().map(PathBuf::from).collect(),
+        height: matches.value_of("height").map(String::from),
+        height_scale: matches.value_of("height_scale").unwrap().parse::<f32>().unwrap_or(50.0),
+    }
+}
+
+fn parse_render_settings(settings: &Settings) -> RenderSettings {
+    RenderSettings {
+        shading: parse_or_exit::<Shading>(&settings.value_of("shading").unwrap()),
+        color_space: parse_or_exit::<ColorSpace>(&settings.value_of("color_space").unwrap()),
+        palette: settings.value_of("palette"),
+        palette_method: parse_or_exit::<QuantizationMethod>(&settings.value_of("palette_method").unwrap()),
+        palette_metric: parse_or_exit::<ColorSpace>(&settings.value_of("palette_metric").unwrap()),
+        dither: settings.is_present("dither"),
+        wireframe: settings.value_of("wireframe").map(|mode| parse_or_exit::<WireframeMode>(&mode)),
+        wireframe_style: WireframeStyle {
+            color: parse_hex_color(&settings.value_of("line_color").unwrap()).unwrap_or_else(|err| exit_with_error(err)),
+            width: settings.value_of("line_width").unwrap().parse::<f32>().unwrap_or(1.0),
+            opacity: settings.value_of("line_opacity").unwrap().parse::<f32>().unwrap_or(1.0),
+            anti_aliasing: !settings.is_present("no_anti_aliasing"),
+            vertex_radius: settings.value_of("vertex_radius").unwrap().parse::<f32>().unwrap_or(0.0),
+        },
+        background: parse_hex_color(&settings.value_of("background").unwrap()).unwrap_or_else(|err| exit_with_error(err)),
+    }
+}
+
+fn print_render_settings(render: &RenderSettings) {
+    info!("- Shading:          {:?}", render.shading);
+    info!("- Color space:      {:?}", render.color_space);
+    if let Some(palette) = &render.palette {
+        info!("- Palette:          {} ({:?}, {:?} metric{})", palette, render.palette_method, render.palette_metric,
+            if render.dither { ", dithered" } else { "" });
+    }
+    if let Some(mode) = &render.wireframe {
+        info!("- Wireframe:        {:?}", mode);
+    }
+}
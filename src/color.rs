@@ -0,0 +1,362 @@
+// Standard library imports
+use std::str::FromStr;
+
+// Community library imports
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+
+// The color space in which colors are averaged, interpolated and compared.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorSpace {
+    // The sRGB encoded values as they are.
+    #[default]
+    Srgb,
+    // Linear light, in which mixtures of bright and dark colors are not
+    // darkened.
+    Linear,
+    // CIELAB (D65) with all channels divided by 100.
+    Lab,
+    // Oklab, a perceptually uniform space with smoother hue gradients.
+    Oklab,
+    // HSV with hue and saturation as a point on the color wheel, so that
+    // hues are mixed along the shorter way around and grays have no hue.
+    Hsv,
+}
+
+impl FromStr for ColorSpace {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorSpace, String> {
+        match s {
+            "srgb" => Ok(ColorSpace::Srgb),
+            "linear" => Ok(ColorSpace::Linear),
+            "lab" => Ok(ColorSpace::Lab),
+            "oklab" => Ok(ColorSpace::Oklab),
+            "hsv" => Ok(ColorSpace::Hsv),
+            _ => Err(format!("unknown color space '{}'", s)),
+        }
+    }
+}
+
+impl ColorSpace {
+    // Converts an sRGB encoded color into this color space. Alpha is kept.
+    pub fn decode(self, c: Rgba<f32>) -> Rgba<f32> {
+        let rgb = [c[0], c[1], c[2]];
+        let converted = match self {
+            ColorSpace::Srgb => return c,
+            ColorSpace::Linear => rgb.map(srgb_to_linear),
+            ColorSpace::Lab => linear_to_lab(rgb.map(srgb_to_linear)),
+            ColorSpace::Oklab => linear_to_oklab(rgb.map(srgb_to_linear)),
+            ColorSpace::Hsv => srgb_to_hsv(rgb),
+        };
+        Rgba([converted[0], converted[1], converted[2], c[3]])
+    }
+
+    // Converts a color of this color space back to sRGB. Alpha is kept.
+    // Colors outside of the sRGB gamut are clipped.
+    pub fn encode(self, c: Rgba<f32>) -> Rgba<f32> {
+        let values = [c[0], c[1], c[2]];
+        let rgb = match self {
+            ColorSpace::Srgb => return c,
+            ColorSpace::Linear => values.map(linear_to_srgb),
+            ColorSpace::Lab => lab_to_linear(values).map(|c| linear_to_srgb(c.clamp(0.0, 1.0))),
+            ColorSpace::Oklab => oklab_to_linear(values).map(|c| linear_to_srgb(c.clamp(0.0, 1.0))),
+            ColorSpace::Hsv => hsv_to_srgb(values),
+        };
+        Rgba([rgb[0], rgb[1], rgb[2], c[3]])
+    }
+}
+
+// Decodes an sRGB encoded channel in [0, 1] to linear light.
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// Encodes a linear light channel in [0, 1] as sRGB.
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn multiply(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+// Linear sRGB to CIE XYZ relative to the D65 white point and back. The
+// published constants are kept as they are, even where f32 is less precise.
+#[allow(clippy::excessive_precision)]
+const LINEAR_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
+#[allow(clippy::excessive_precision)]
+const XYZ_TO_LINEAR: [[f32; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.9692660, 1.8760108, 0.0415560],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+const D65_WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+const LAB_DELTA: f32 = 6.0 / 29.0;
+
+fn linear_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let f = |t: f32| {
+        if t > LAB_DELTA.powi(3) {
+            t.cbrt()
+        } else {
+            t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0
+        }
+    };
+    let xyz = multiply(&LINEAR_TO_XYZ, rgb);
+    let (fx, fy, fz) = (f(xyz[0] / D65_WHITE[0]), f(xyz[1] / D65_WHITE[1]), f(xyz[2] / D65_WHITE[2]));
+    [(116.0 * fy - 16.0) / 100.0, 5.0 * (fx - fy), 2.0 * (fy - fz)]
+}
+
+fn lab_to_linear(lab: [f32; 3]) -> [f32; 3] {
+    let f_inv = |t: f32| {
+        if t > LAB_DELTA {
+            t.powi(3)
+        } else {
+            3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0)
+        }
+    };
+    let fy = (lab[0] * 100.0 + 16.0) / 116.0;
+    let fx = fy + lab[1] / 5.0;
+    let fz = fy - lab[2] / 2.0;
+    let xyz = [D65_WHITE[0] * f_inv(fx), D65_WHITE[1] * f_inv(fy), D65_WHITE[2] * f_inv(fz)];
+    multiply(&XYZ_TO_LINEAR, xyz)
+}
+
+// Matrices of the Oklab definition by Björn Ottosson
+#[allow(clippy::excessive_precision)]
+const LINEAR_TO_LMS: [[f32; 3]; 3] = [
+    [0.4122214708, 0.5363325363, 0.0514459929],
+    [0.2119034982, 0.6806995451, 0.1073969566],
+    [0.0883024619, 0.2817188376, 0.6299787005],
+];
+#[allow(clippy::excessive_precision)]
+const LMS_TO_OKLAB: [[f32; 3]; 3] = [
+    [0.2104542553, 0.7936177850, -0.0040720468],
+    [1.9779984951, -2.4285922050, 0.4505937099],
+    [0.0259040371, 0.7827717662, -0.8086757660],
+];
+#[allow(clippy::excessive_precision)]
+const OKLAB_TO_LMS: [[f32; 3]; 3] = [
+    [1.0, 0.3963377774, 0.2158037573],
+    [1.0, -0.1055613458, -0.0638541728],
+    [1.0, -0.0894841775, -1.2914855480],
+];
+#[allow(clippy::excessive_precision)]
+const LMS_TO_LINEAR: [[f32; 3]; 3] = [
+    [4.0767416621, -3.3077115913, 0.2309699292],
+    [-1.2684380046, 2.6097574011, -0.3413193965],
+    [-0.0041960863, -0.7034186147, 1.7076147010],
+];
+
+fn linear_to_oklab(rgb: [f32; 3]) -> [f32; 3] {
+    multiply(&LMS_TO_OKLAB, multiply(&LINEAR_TO_LMS, rgb).map(f32::cbrt))
+}
+
+fn oklab_to_linear(lab: [f32; 3]) -> [f32; 3] {
+    multiply(&LMS_TO_LINEAR, multiply(&OKLAB_TO_LMS, lab).map(|c| c.powi(3)))
+}
+
+// HSV as [saturation * cos(hue), saturation * sin(hue), value]
+fn srgb_to_hsv(rgb: [f32; 3]) -> [f32; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let chroma = max - rgb[0].min(rgb[1]).min(rgb[2]);
+    if chroma <= 0.0 {
+        return [0.0, 0.0, max];
+    }
+
+    let sector = if max == rgb[0] {
+        ((rgb[1] - rgb[2]) / chroma).rem_euclid(6.0)
+    } else if max == rgb[1] {
+        (rgb[2] - rgb[0]) / chroma + 2.0
+    } else {
+        (rgb[0] - rgb[1]) / chroma + 4.0
+    };
+    let hue = sector * std::f32::consts::PI / 3.0;
+    let saturation = chroma / max;
+    [saturation * hue.cos(), saturation * hue.sin(), max]
+}
+
+fn hsv_to_srgb(hsv: [f32; 3]) -> [f32; 3] {
+    let value = hsv[2].clamp(0.0, 1.0);
+    let saturation = hsv[0].hypot(hsv[1]).min(1.0);
+    let sector = (hsv[1].atan2(hsv[0]) * 3.0 / std::f32::consts::PI).rem_euclid(6.0);
+
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r + m, g + m, b + m]
+}
+
+// Parses colors written as `rrggbb` or `rrggbbaa` in hexadecimal, with or
+// without a leading `#`.
+pub fn parse_hex_color(s: &str) -> Result<Rgba<f32>, String> {
+    let hex = s.trim().trim_start_matches('#');
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex color '{}'", s));
+    }
+
+    let mut c = [1.0; 4];
+    for (i, channel) in c.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap() as f32 / 255.0;
+    }
+    Ok(Rgba(c))
+}
+
+// Largest difference between the alpha and the alpha-premultiplied color
+// channels of two colors.
+pub fn premultiplied_difference(a: Rgba<f32>, b: Rgba<f32>) -> f32 {
+    let mut diff = (a[3] - b[3]).abs();
+    for i in 0..3 {
+        diff = diff.max((a[i] * a[3] - b[i] * b[3]).abs());
+    }
+    diff
+}
+
+// Composites `src` with the given opacity over `dst` (Porter-Duff over),
+// taking the alpha of both colors into account.
+pub fn blend_over(dst: Rgba<f32>, src: Rgba<f32>, opacity: f32) -> Rgba<f32> {
+    let alpha_src = opacity * src[3];
+    let alpha_dst = dst[3];
+    let alpha_out = alpha_src + alpha_dst * (1.0 - alpha_src);
+    if alpha_out <= 0.0 {
+        return Rgba([0.0; 4]);
+    }
+
+    let mut c = [0.0; 4];
+    for i in 0..3 {
+        c[i] = (src[i] * alpha_src + dst[i] * alpha_dst * (1.0 - alpha_src)) / alpha_out;
+    }
+    c[3] = alpha_out;
+    Rgba(c)
+}
+
+#[cfg(test)]
+mod color_tests {
+    use super::*;
+
+    // Channels are compared with a precision of three decimals
+    fn rounded(c: Rgba<f32>) -> String {
+        format!("{:.3?}", c.0)
+    }
+
+    #[test]
+    fn test_color_space_from_str() {
+        assert_eq!("srgb".parse::<ColorSpace>(), Ok(ColorSpace::Srgb));
+        assert_eq!("linear".parse::<ColorSpace>(), Ok(ColorSpace::Linear));
+        assert_eq!("lab".parse::<ColorSpace>(), Ok(ColorSpace::Lab));
+        assert_eq!("oklab".parse::<ColorSpace>(), Ok(ColorSpace::Oklab));
+        assert_eq!("hsv".parse::<ColorSpace>(), Ok(ColorSpace::Hsv));
+        assert!("rgb".parse::<ColorSpace>().is_err());
+    }
+
+    #[test]
+    fn test_srgb_linear_conversion() {
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert_eq!(srgb_to_linear(1.0), 1.0);
+        assert!((srgb_to_linear(0.5) - 0.2140).abs() < 1e-4);
+        assert!((srgb_to_linear(128.0 / 255.0) - 0.2159).abs() < 1e-4);
+        assert!((srgb_to_linear(0.02) - 0.02 / 12.92).abs() < 1e-7);
+
+        assert_eq!(linear_to_srgb(0.0), 0.0);
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((linear_to_srgb(0.5) - 0.7354).abs() < 1e-4);
+
+        for i in 0..=255 {
+            let c = i as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_color_space_conversion() {
+        let c = Rgba([0.5, 1.0, 0.0, 0.5]);
+
+        assert_eq!(ColorSpace::Srgb.decode(c), c);
+        assert_eq!(ColorSpace::Linear.decode(c)[3], 0.5);
+        assert!((ColorSpace::Linear.decode(c)[0] - 0.2140).abs() < 1e-4);
+        assert!((ColorSpace::Linear.encode(ColorSpace::Linear.decode(c))[0] - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_color_space_reference_values() {
+        let red = Rgba([1.0, 0.0, 0.0, 1.0]);
+        let white = Rgba([1.0, 1.0, 1.0, 1.0]);
+
+        assert_eq!(rounded(ColorSpace::Lab.decode(red)), rounded(Rgba([0.5324, 0.8009, 0.6720, 1.0])));
+        assert_eq!(rounded(ColorSpace::Lab.decode(white)), rounded(Rgba([1.0, 0.0, 0.0, 1.0])));
+        assert_eq!(rounded(ColorSpace::Oklab.decode(red)), rounded(Rgba([0.6280, 0.2249, 0.1258, 1.0])));
+        assert_eq!(rounded(ColorSpace::Oklab.decode(white)), rounded(Rgba([1.0, 0.0, 0.0, 1.0])));
+        assert_eq!(rounded(ColorSpace::Hsv.decode(red)), rounded(Rgba([1.0, 0.0, 1.0, 1.0])));
+        assert_eq!(rounded(ColorSpace::Hsv.decode(Rgba([0.0, 0.0, 0.5, 1.0]))), rounded(Rgba([-0.5, -0.866, 0.5, 1.0])));
+        assert_eq!(rounded(ColorSpace::Hsv.decode(Rgba([0.5, 0.5, 0.5, 1.0]))), rounded(Rgba([0.0, 0.0, 0.5, 1.0])));
+    }
+
+    #[test]
+    fn test_color_space_roundtrip() {
+        let spaces = [ColorSpace::Srgb, ColorSpace::Linear, ColorSpace::Lab, ColorSpace::Oklab, ColorSpace::Hsv];
+        for space in spaces.iter() {
+            for r in 0..=4 {
+                for g in 0..=4 {
+                    for b in 0..=4 {
+                        let c = Rgba([r as f32 / 4.0, g as f32 / 4.0, b as f32 / 4.0, 0.5]);
+                        assert_eq!(rounded(space.encode(space.decode(c))), rounded(c), "{:?}", space);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff8000"), Ok(Rgba([1.0, 128.0 / 255.0, 0.0, 1.0])));
+        assert_eq!(parse_hex_color("00000080"), Ok(Rgba([0.0, 0.0, 0.0, 128.0 / 255.0])));
+        assert!(parse_hex_color("#fff").is_err());
+        assert!(parse_hex_color("gg0000").is_err());
+    }
+
+    #[test]
+    fn test_premultiplied_difference() {
+        assert_eq!(premultiplied_difference(Rgba([0.25, 0.5, 0.75, 1.0]), Rgba([0.25, 0.625, 0.75, 1.0])), 0.125);
+        assert_eq!(premultiplied_difference(Rgba([0.1, 0.2, 0.3, 1.0]), Rgba([0.1, 0.2, 0.3, 0.75])), 0.25);
+        assert_eq!(premultiplied_difference(Rgba([1.0, 0.0, 0.0, 0.0]), Rgba([0.0, 0.0, 1.0, 0.0])), 0.0);
+        assert_eq!(premultiplied_difference(Rgba([0.8, 0.0, 0.0, 0.5]), Rgba([0.0, 0.0, 0.0, 0.5])), 0.4);
+    }
+
+    #[test]
+    fn test_blend_over() {
+        let white = Rgba([1.0, 1.0, 1.0, 1.0]);
+        let black = Rgba([0.0, 0.0, 0.0, 1.0]);
+        let transparent = Rgba([0.0; 4]);
+
+        assert_eq!(blend_over(white, black, 1.0), black);
+        assert_eq!(blend_over(white, black, 0.0), white);
+        assert_eq!(blend_over(white, black, 0.5), Rgba([0.5, 0.5, 0.5, 1.0]));
+        assert_eq!(blend_over(transparent, black, 0.5), Rgba([0.0, 0.0, 0.0, 0.5]));
+        assert_eq!(blend_over(transparent, transparent, 1.0), transparent);
+    }
+}
@@ -1,10 +1,219 @@
 // Standard library imports
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
+// Community library imports
+use image::Rgba;
+
+// Crate imports
+use crate::animation::AnimationTiming;
+use crate::color::ColorSpace;
+use crate::filter::Filter;
+use crate::importance::Region;
+use crate::locate::{InsertionOrder, LocateStrategy};
+use crate::palette::QuantizationMethod;
+use crate::rasterization::Shading;
+use crate::wireframe::{WireframeMode, WireframeStyle};
+
+// The subcommand to run with its options.
+pub enum Command {
+    Triangulate(Box<Config>),
+    Render(RenderConfig),
+    Export(ExportConfig),
+    Inspect(InspectConfig),
+}
+
+// Options of the `triangulate` subcommand.
 pub struct Config {
-    pub path_in: PathBuf,
-    pub path_out: PathBuf,
+    pub inputs: Vec<String>,
+    pub recursive: bool,
+    pub dir_out: Option<PathBuf>,
+    pub n_jobs: Option<usize>,
+    // Time after which the refinement of an image stops.
+    pub time_budget: Option<Duration>,
+    pub locate: LocateStrategy,
+    pub insertion_order: InsertionOrder,
     pub n_initial_points: usize,
-    pub n_iterations: i32,
+    pub seed: u64,
+    pub n_iterations: usize,
     pub max_diff: i32,
+    pub skip_transparent: bool,
+    pub prefilter: Option<Filter>,
+    pub sample_original: bool,
+    pub pyramid_levels: usize,
+    pub adaptive_strength: Option<f32>,
+    pub adaptive_radius: u32,
+    pub path_roi_mask: Option<PathBuf>,
+    pub roi_regions: Vec<Region>,
+    pub roi_strength: f32,
+    pub paths_export: Vec<PathBuf>,
+    pub height: Option<String>,
+    pub height_scale: f32,
+    pub render: RenderSettings,
+    pub refine_to_palette: bool,
+    pub path_error_map: Option<PathBuf>,
+    pub path_animation: Option<PathBuf>,
+    pub animation_timing: AnimationTiming,
+    pub path_mesh_in: Option<PathBuf>,
+    pub path_mesh_out: Option<PathBuf>,
+    pub path_report: Option<PathBuf>,
+}
+
+// The defaults of the command line, except for the seed, which is fixed
+// instead of random
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            inputs: Vec::new(),
+            recursive: false,
+            dir_out: None,
+            n_jobs: None,
+            time_budget: None,
+            locate: LocateStrategy::Walk,
+            insertion_order: InsertionOrder::Hilbert,
+            n_initial_points: 100,
+            seed: 0,
+            n_iterations: 4,
+            max_diff: 15,
+            skip_transparent: false,
+            prefilter: None,
+            sample_original: false,
+            pyramid_levels: 1,
+            adaptive_strength: None,
+            adaptive_radius: 3,
+            path_roi_mask: None,
+            roi_regions: Vec::new(),
+            roi_strength: 4.0,
+            paths_export: Vec::new(),
+            height: None,
+            height_scale: 50.0,
+            render: RenderSettings::default(),
+            refine_to_palette: false,
+            path_error_map: None,
+            path_animation: None,
+            animation_timing: AnimationTiming { frame_delay: 500, hold: 3000 },
+            path_mesh_in: None,
+            path_mesh_out: None,
+            path_report: None,
+        }
+    }
+}
+
+// How a mesh is turned into an image, shared by `triangulate` and `render`.
+pub struct RenderSettings {
+    pub shading: Shading,
+    pub color_space: ColorSpace,
+    pub palette: Option<String>,
+    pub palette_method: QuantizationMethod,
+    pub palette_metric: ColorSpace,
+    pub dither: bool,
+    pub wireframe: Option<WireframeMode>,
+    pub wireframe_style: WireframeStyle,
+    pub background: Rgba<f32>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            shading: Shading::Flat,
+            color_space: ColorSpace::Srgb,
+            palette: None,
+            palette_method: QuantizationMethod::KMeans,
+            palette_metric: ColorSpace::Oklab,
+            dither: false,
+            wireframe: None,
+            wireframe_style: WireframeStyle {
+                color: Rgba([0.0, 0.0, 0.0, 1.0]),
+                width: 1.0,
+                opacity: 1.0,
+                anti_aliasing: true,
+                vertex_radius: 0.0,
+            },
+            background: Rgba([1.0, 1.0, 1.0, 1.0]),
+        }
+    }
+}
+
+// Options of the `render` subcommand.
+pub struct RenderConfig {
+    pub path_mesh: PathBuf,
+    pub path_out: PathBuf,
+    pub size: OutputSize,
+    pub render: RenderSettings,
+}
+
+// Options of the `export` subcommand.
+pub struct ExportConfig {
+    pub path_mesh: PathBuf,
+    pub paths_export: Vec<PathBuf>,
+    pub height: Option<String>,
+    pub height_scale: f32,
+}
+
+// Options of the `inspect` subcommand.
+pub struct InspectConfig {
+    pub path_mesh: PathBuf,
+    pub json: bool,
+}
+
+// Size of a rendered image relative to the image the mesh was created for.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputSize {
+    Original,
+    Scale(f32),
+    // The width, the height following from the aspect ratio.
+    Width(u32),
+    Exact(u32, u32),
+}
+
+impl FromStr for OutputSize {
+    type Err = String;
+
+    // Parses a width like `800` or a width and height like `800x600`.
+    fn from_str(s: &str) -> Result<OutputSize, String> {
+        let parse = |value: &str| match value.trim().parse::<u32>() {
+            Ok(value) if value > 1 => Ok(value),
+            _ => Err(format!("invalid size '{}'", s)),
+        };
+        match s.split_once('x') {
+            Some((width, height)) => Ok(OutputSize::Exact(parse(width)?, parse(height)?)),
+            None => Ok(OutputSize::Width(parse(s)?)),
+        }
+    }
+}
+
+impl OutputSize {
+    // The size of the image to render for an image of the given size.
+    pub fn resolve(&self, width: u32, height: u32) -> (u32, u32) {
+        match *self {
+            OutputSize::Original => (width, height),
+            OutputSize::Scale(scale) => {
+                ((width as f32 * scale).round().max(2.0) as u32, (height as f32 * scale).round().max(2.0) as u32)
+            }
+            OutputSize::Width(new_width) => {
+                let new_height = (new_width as f32 * height as f32 / width as f32).round().max(2.0);
+                (new_width, new_height as u32)
+            }
+            OutputSize::Exact(new_width, new_height) => (new_width, new_height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn test_output_size() {
+        assert_eq!("800".parse::<OutputSize>(), Ok(OutputSize::Width(800)));
+        assert_eq!("800x600".parse::<OutputSize>(), Ok(OutputSize::Exact(800, 600)));
+        assert!("800x".parse::<OutputSize>().is_err());
+        assert!("1".parse::<OutputSize>().is_err());
+
+        assert_eq!(OutputSize::Original.resolve(400, 300), (400, 300));
+        assert_eq!(OutputSize::Scale(2.5).resolve(400, 300), (1000, 750));
+        assert_eq!(OutputSize::Width(800).resolve(400, 300), (800, 600));
+        assert_eq!(OutputSize::Exact(10, 10).resolve(400, 300), (10, 10));
+    }
 }
@@ -0,0 +1,636 @@
+// Standard library imports
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Community library imports
+use image::{GrayImage, Rgba};
+use serde_json::json;
+use spade::delaunay::DelaunayLocateStructure;
+
+// Crate imports
+use crate::color::{srgb_to_linear, ColorSpace};
+use crate::interpolation::interpolate_triangle_average_color;
+use crate::point::Point;
+use crate::Mesh;
+
+// Where the Z coordinate of a vertex comes from when the mesh is extruded.
+pub enum HeightSource {
+    // The luminance of the vertex color.
+    Luminance,
+    // A separate grayscale image, stretched over the whole mesh.
+    HeightMap(GrayImage),
+}
+
+// Displacement of the vertices in Z, with heights in [0, 1] multiplied by
+// `scale`.
+pub struct Extrusion {
+    pub source: HeightSource,
+    pub scale: f32,
+}
+
+// Exports the mesh to a format chosen by the file extension: `.msh` (Gmsh MSH
+// 4.1), `.vtk` (legacy VTK unstructured grid), `.node`/`.ele`/`.poly`
+// (Shewchuk's Triangle, all three files are written), `.svg` (flat colored
+// polygons, averaged in the given color space), `.obj` (Wavefront OBJ) or
+// `.glb` (binary glTF 2.0).
+pub fn export_mesh<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    path: &Path,
+    extrusion: Option<&Extrusion>,
+    space: ColorSpace,
+) -> io::Result<()> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("msh") => write_msh(mesh, &mut create_file(path)?),
+        Some("vtk") => write_vtk(mesh, &mut create_file(path)?),
+        Some("obj") => write_obj(mesh, extrusion, &mut create_file(path)?),
+        Some("glb") => write_glb(mesh, extrusion, &mut create_file(path)?),
+        Some("svg") => {
+            let face_colors: Vec<Rgba<f32>> = mesh
+                .triangles()
+                .map(|face| interpolate_triangle_average_color(&face.as_triangle(), space))
+                .collect();
+            let (xmax, ymax) = extent(mesh);
+            write_svg(mesh, xmax as u32 + 1, ymax as u32 + 1, &face_colors, &mut create_file(path)?)
+        }
+        Some("node") | Some("ele") | Some("poly") => {
+            write_triangle_node(mesh, &mut create_file(&path.with_extension("node"))?)?;
+            write_triangle_ele(mesh, &mut create_file(&path.with_extension("ele"))?)?;
+            write_triangle_poly(mesh, &mut create_file(&path.with_extension("poly"))?)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported export format of {}", path.display()),
+        )),
+    }
+}
+
+fn create_file(path: &Path) -> io::Result<BufWriter<File>> {
+    Ok(BufWriter::new(File::create(path)?))
+}
+
+// Vertex indices of every triangle. Spade hands out fixed vertex handles
+// as contiguous indices starting at 0 as long as no vertex is removed.
+fn collect_faces<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>) -> Vec<[usize; 3]> {
+    mesh.triangles()
+        .map(|face| {
+            let triangle = face.as_triangle();
+            [triangle[0].fix(), triangle[1].fix(), triangle[2].fix()]
+        })
+        .collect()
+}
+
+// Largest coordinates of the vertices, which are the corners of the image
+// the mesh was created for
+fn extent<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>) -> (f32, f32) {
+    let (mut xmax, mut ymax) = (0.0f32, 0.0f32);
+    for vertex in mesh.vertices() {
+        xmax = xmax.max(vertex.x);
+        ymax = ymax.max(vertex.y);
+    }
+    (xmax, ymax)
+}
+
+// Edges on the convex hull, i.e. edges adjacent to the infinite face.
+fn collect_boundary_edges<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>) -> Vec<[usize; 2]> {
+    let infinite_face = mesh.infinite_face().fix();
+    mesh.edges()
+        .filter(|edge| edge.face().fix() == infinite_face || edge.sym().face().fix() == infinite_face)
+        .map(|edge| [edge.from().fix(), edge.to().fix()])
+        .collect()
+}
+
+// Writes the mesh in the Gmsh MSH 4.1 ASCII format. All triangles belong to
+// a single discrete surface and the vertex colors are stored as node data.
+pub fn write_msh<W: Write, L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, w: &mut W) -> io::Result<()> {
+    let faces = collect_faces(mesh);
+    let n_vertices = mesh.num_vertices();
+
+    let (mut xmin, mut xmax, mut ymin, mut ymax) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for vertex in mesh.vertices() {
+        xmin = xmin.min(vertex.x);
+        xmax = xmax.max(vertex.x);
+        ymin = ymin.min(vertex.y);
+        ymax = ymax.max(vertex.y);
+    }
+
+    writeln!(w, "$MeshFormat\n4.1 0 8\n$EndMeshFormat")?;
+
+    writeln!(w, "$Entities\n0 0 1 0")?;
+    writeln!(w, "1 {} {} 0 {} {} 0 0 0", xmin, ymin, xmax, ymax)?;
+    writeln!(w, "$EndEntities")?;
+
+    writeln!(w, "$Nodes")?;
+    writeln!(w, "1 {} 1 {}", n_vertices, n_vertices)?;
+    writeln!(w, "2 1 0 {}", n_vertices)?;
+    for vertex in mesh.vertices() {
+        writeln!(w, "{}", vertex.fix() + 1)?;
+    }
+    for vertex in mesh.vertices() {
+        writeln!(w, "{} {} 0", vertex.x, vertex.y)?;
+    }
+    writeln!(w, "$EndNodes")?;
+
+    writeln!(w, "$Elements")?;
+    writeln!(w, "1 {} 1 {}", faces.len(), faces.len())?;
+    writeln!(w, "2 1 2 {}", faces.len())?;
+    for (i, face) in faces.iter().enumerate() {
+        writeln!(w, "{} {} {} {}", i + 1, face[0] + 1, face[1] + 1, face[2] + 1)?;
+    }
+    writeln!(w, "$EndElements")?;
+
+    writeln!(w, "$NodeData\n1\n\"RGB\"\n1\n0.0\n3\n0\n3\n{}", n_vertices)?;
+    for vertex in mesh.vertices() {
+        writeln!(w, "{} {} {} {}", vertex.fix() + 1, vertex.c[0], vertex.c[1], vertex.c[2])?;
+    }
+    writeln!(w, "$EndNodeData")
+}
+
+// Writes the mesh as a legacy ASCII VTK unstructured grid. Vertex colors are
+// stored as point data, the average color of every triangle as cell data.
+pub fn write_vtk<W: Write, L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, w: &mut W) -> io::Result<()> {
+    let faces = collect_faces(mesh);
+    let n_vertices = mesh.num_vertices();
+
+    writeln!(w, "# vtk DataFile Version 3.0")?;
+    writeln!(w, "AdapTri triangular mesh")?;
+    writeln!(w, "ASCII")?;
+    writeln!(w, "DATASET UNSTRUCTURED_GRID")?;
+
+    writeln!(w, "POINTS {} float", n_vertices)?;
+    for vertex in mesh.vertices() {
+        writeln!(w, "{} {} 0", vertex.x, vertex.y)?;
+    }
+
+    writeln!(w, "CELLS {} {}", faces.len(), 4 * faces.len())?;
+    for face in faces.iter() {
+        writeln!(w, "3 {} {} {}", face[0], face[1], face[2])?;
+    }
+    writeln!(w, "CELL_TYPES {}", faces.len())?;
+    for _ in faces.iter() {
+        writeln!(w, "5")?;
+    }
+
+    writeln!(w, "POINT_DATA {}", n_vertices)?;
+    writeln!(w, "COLOR_SCALARS vertex_color 3")?;
+    for vertex in mesh.vertices() {
+        writeln!(
+            w,
+            "{} {} {}",
+            vertex.c[0],
+            vertex.c[1],
+            vertex.c[2]
+        )?;
+    }
+
+    writeln!(w, "CELL_DATA {}", faces.len())?;
+    writeln!(w, "COLOR_SCALARS face_color 3")?;
+    for face in mesh.triangles() {
+        let c = interpolate_triangle_average_color(&face.as_triangle(), ColorSpace::Srgb);
+        writeln!(
+            w,
+            "{} {} {}",
+            c[0],
+            c[1],
+            c[2]
+        )?;
+    }
+    Ok(())
+}
+
+// Writes the vertices in Triangle's `.node` format. The vertex colors are
+// stored as three attributes and hull vertices get the boundary marker 1.
+pub fn write_triangle_node<W: Write, L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, w: &mut W) -> io::Result<()> {
+    let mut on_boundary = vec![false; mesh.num_vertices()];
+    for edge in collect_boundary_edges(mesh) {
+        on_boundary[edge[0]] = true;
+        on_boundary[edge[1]] = true;
+    }
+
+    writeln!(w, "{} 2 3 1", mesh.num_vertices())?;
+    for vertex in mesh.vertices() {
+        writeln!(
+            w,
+            "{} {} {} {} {} {} {}",
+            vertex.fix() + 1,
+            vertex.x,
+            vertex.y,
+            vertex.c[0],
+            vertex.c[1],
+            vertex.c[2],
+            on_boundary[vertex.fix()] as u8
+        )?;
+    }
+    Ok(())
+}
+
+// Writes the triangles in Triangle's `.ele` format.
+pub fn write_triangle_ele<W: Write, L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, w: &mut W) -> io::Result<()> {
+    let faces = collect_faces(mesh);
+
+    writeln!(w, "{} 3 0", faces.len())?;
+    for (i, face) in faces.iter().enumerate() {
+        writeln!(w, "{} {} {} {}", i + 1, face[0] + 1, face[1] + 1, face[2] + 1)?;
+    }
+    Ok(())
+}
+
+// Writes the convex hull as segments in Triangle's `.poly` format. The
+// vertices are not repeated and are read from the `.node` file instead.
+pub fn write_triangle_poly<W: Write, L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, w: &mut W) -> io::Result<()> {
+    let edges = collect_boundary_edges(mesh);
+
+    writeln!(w, "0 2 3 1")?;
+    writeln!(w, "{} 1", edges.len())?;
+    for (i, edge) in edges.iter().enumerate() {
+        writeln!(w, "{} {} {} 1", i + 1, edge[0] + 1, edge[1] + 1)?;
+    }
+    writeln!(w, "0")
+}
+
+// Writes the mesh as a Wavefront OBJ file with the vertex colors appended to
+// the vertex positions, as most viewers read them.
+pub fn write_obj<W: Write, L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    extrusion: Option<&Extrusion>,
+    w: &mut W,
+) -> io::Result<()> {
+    let (xmax, ymax) = extent(mesh);
+
+    writeln!(w, "# AdapTri triangular mesh")?;
+    for vertex in mesh.vertices() {
+        let z = match extrusion {
+            Some(extrusion) => extrusion.scale * vertex_height(&vertex, &extrusion.source, xmax, ymax),
+            None => 0.0,
+        };
+        // Subtracting from zero does not write the top row as -0
+        writeln!(w, "v {} {} {} {} {} {}", vertex.x, 0.0 - vertex.y, z, vertex.c[0], vertex.c[1], vertex.c[2])?;
+    }
+    // The winding is reversed as the Y axis is flipped
+    for face in collect_faces(mesh) {
+        writeln!(w, "f {} {} {}", face[0] + 1, face[2] + 1, face[1] + 1)?;
+    }
+    Ok(())
+}
+
+// Writes the triangles as SVG polygons of the given colors, one per triangle
+// in the order of `mesh.triangles()`, on a canvas of the given size.
+pub fn write_svg<W: Write, L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: u32,
+    height: u32,
+    face_colors: &[Rgba<f32>],
+    w: &mut W,
+) -> io::Result<()> {
+    writeln!(
+        w,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">",
+        width, height, width, height
+    )?;
+    for (face, c) in mesh.triangles().zip(face_colors.iter()) {
+        if c[3] <= 0.0 {
+            continue;
+        }
+        let points: Vec<String> = face
+            .as_triangle()
+            .iter()
+            .map(|vertex| format!("{},{}", vertex.x + 0.5, vertex.y + 0.5))
+            .collect();
+        let color = format!(
+            "#{:02x}{:02x}{:02x}",
+            (c[0].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (c[1].clamp(0.0, 1.0) * 255.0).round() as u8,
+            (c[2].clamp(0.0, 1.0) * 255.0).round() as u8
+        );
+        if c[3] < 1.0 {
+            writeln!(w, "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>", points.join(" "), color, c[3])?;
+        } else {
+            writeln!(
+                w,
+                "<polygon points=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"0.5\" stroke-linejoin=\"round\"/>",
+                points.join(" "),
+                color,
+                color
+            )?;
+        }
+    }
+    writeln!(w, "</svg>")
+}
+
+// Writes the mesh as binary glTF 2.0 with linear vertex colors and an unlit
+// material.
+pub fn write_glb<W: Write, L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    extrusion: Option<&Extrusion>,
+    w: &mut W,
+) -> io::Result<()> {
+    let n_vertices = mesh.num_vertices();
+    let faces = collect_faces(mesh);
+    let (xmax, ymax) = extent(mesh);
+
+    let mut positions = Vec::with_capacity(n_vertices * 12);
+    let mut colors = Vec::with_capacity(n_vertices * 12);
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for vertex in mesh.vertices() {
+        let z = match extrusion {
+            Some(extrusion) => extrusion.scale * vertex_height(&vertex, &extrusion.source, xmax, ymax),
+            None => 0.0,
+        };
+        let position = [vertex.x, -vertex.y, z];
+        for i in 0..3 {
+            min[i] = min[i].min(position[i]);
+            max[i] = max[i].max(position[i]);
+            positions.extend_from_slice(&position[i].to_le_bytes());
+            // glTF expects vertex colors in linear light
+            colors.extend_from_slice(&srgb_to_linear(vertex.c[i]).to_le_bytes());
+        }
+    }
+
+    // Flipping the Y axis turns counter clockwise triangles into clockwise
+    // ones, so the winding is reversed to keep them facing the viewer
+    let mut indices = Vec::with_capacity(faces.len() * 12);
+    for face in faces.iter() {
+        for index in [face[0], face[2], face[1]].iter() {
+            indices.extend_from_slice(&(*index as u32).to_le_bytes());
+        }
+    }
+
+    let mut bin = Vec::with_capacity(positions.len() + colors.len() + indices.len());
+    bin.extend_from_slice(&positions);
+    bin.extend_from_slice(&colors);
+    bin.extend_from_slice(&indices);
+    pad_to_four_bytes(&mut bin, 0);
+
+    let gltf = json!({
+        "asset": { "version": "2.0", "generator": "AdapTri" },
+        "extensionsUsed": ["KHR_materials_unlit"],
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "mesh": 0 }],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "COLOR_0": 1 },
+                "indices": 2,
+                "material": 0,
+            }],
+        }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [1.0, 1.0, 1.0, 1.0],
+                "metallicFactor": 0.0,
+                "roughnessFactor": 1.0,
+            },
+            "doubleSided": true,
+            "extensions": { "KHR_materials_unlit": {} },
+        }],
+        "buffers": [{ "byteLength": bin.len() }],
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": positions.len(), "target": 34962 },
+            { "buffer": 0, "byteOffset": positions.len(), "byteLength": colors.len(), "target": 34962 },
+            {
+                "buffer": 0,
+                "byteOffset": positions.len() + colors.len(),
+                "byteLength": indices.len(),
+                "target": 34963,
+            },
+        ],
+        "accessors": [
+            {
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": n_vertices,
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            },
+            { "bufferView": 1, "componentType": 5126, "count": n_vertices, "type": "VEC3" },
+            { "bufferView": 2, "componentType": 5125, "count": faces.len() * 3, "type": "SCALAR" },
+        ],
+    });
+    let mut json = serde_json::to_vec(&gltf)?;
+    pad_to_four_bytes(&mut json, b' ');
+
+    let total_length = 12 + 8 + json.len() + 8 + bin.len();
+    w.write_all(b"glTF")?;
+    w.write_all(&2u32.to_le_bytes())?;
+    w.write_all(&(total_length as u32).to_le_bytes())?;
+    w.write_all(&(json.len() as u32).to_le_bytes())?;
+    w.write_all(b"JSON")?;
+    w.write_all(&json)?;
+    w.write_all(&(bin.len() as u32).to_le_bytes())?;
+    w.write_all(b"BIN\0")?;
+    w.write_all(&bin)
+}
+
+// Height of a vertex in [0, 1]. The height map is stretched over the extent
+// of the mesh, which spans the whole input image.
+fn vertex_height(vertex: &Point, source: &HeightSource, xmax: f32, ymax: f32) -> f32 {
+    match source {
+        HeightSource::Luminance => {
+            0.2126 * vertex.c[0] + 0.7152 * vertex.c[1] + 0.0722 * vertex.c[2]
+        }
+        HeightSource::HeightMap(height_map) => {
+            let col = (vertex.x / xmax.max(1.0) * (height_map.width() - 1) as f32).round() as u32;
+            let row = (vertex.y / ymax.max(1.0) * (height_map.height() - 1) as f32).round() as u32;
+            height_map.get_pixel(col, row)[0] as f32 / 255.0
+        }
+    }
+}
+
+fn pad_to_four_bytes(buffer: &mut Vec<u8>, padding: u8) {
+    let padded_length = (buffer.len() + 3) & !3;
+    buffer.resize(padded_length, padding);
+}
+
+#[cfg(test)]
+mod export_tests {
+    use image::{Luma, Rgba};
+    use crate::test_util::create_square_triangulation;
+    use super::*;
+
+    // Red, green, blue and white corners
+    const CORNERS: [Rgba<f32>; 4] =
+        [Rgba([1.0, 0.0, 0.0, 1.0]), Rgba([0.0, 1.0, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 1.0]), Rgba([1.0; 4])];
+
+    fn write_to_string<F>(writer: F) -> String
+    where
+        F: Fn(&mut Vec<u8>) -> io::Result<()>,
+    {
+        let mut buffer = Vec::new();
+        writer(&mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn test_collect_boundary_edges() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+
+        // The diagonal is the only interior edge of the square
+        assert_eq!(mesh.num_edges(), 5);
+        assert_eq!(collect_boundary_edges(&mesh).len(), 4);
+    }
+
+    #[test]
+    fn test_write_msh() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let msh = write_to_string(|w| write_msh(&mesh, w));
+        let lines: Vec<&str> = msh.lines().collect();
+
+        assert_eq!(lines[0], "$MeshFormat");
+        assert_eq!(lines[1], "4.1 0 8");
+        assert!(lines.contains(&"1 4 1 4"));
+        assert!(lines.contains(&"2 1 2 2"));
+        assert!(lines.contains(&"0 0 0"));
+        assert!(lines.contains(&"1 1 0 0"));
+        assert!(lines.contains(&"4 1 1 1"));
+        assert_eq!(*lines.last().unwrap(), "$EndNodeData");
+    }
+
+    #[test]
+    fn test_write_vtk() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let vtk = write_to_string(|w| write_vtk(&mesh, w));
+        let lines: Vec<&str> = vtk.lines().collect();
+
+        assert_eq!(lines[0], "# vtk DataFile Version 3.0");
+        assert!(lines.contains(&"POINTS 4 float"));
+        assert!(lines.contains(&"CELLS 2 8"));
+        assert!(lines.contains(&"CELL_TYPES 2"));
+        assert!(lines.contains(&"POINT_DATA 4"));
+        assert!(lines.contains(&"CELL_DATA 2"));
+        assert_eq!(lines.iter().filter(|line| **line == "5").count(), 2);
+    }
+
+    #[test]
+    fn test_write_triangle() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let node = write_to_string(|w| write_triangle_node(&mesh, w));
+        let ele = write_to_string(|w| write_triangle_ele(&mesh, w));
+        let poly = write_to_string(|w| write_triangle_poly(&mesh, w));
+
+        assert_eq!(node.lines().next(), Some("4 2 3 1"));
+        assert_eq!(node.lines().nth(1), Some("1 0 0 1 0 0 1"));
+        assert_eq!(ele.lines().next(), Some("2 3 0"));
+        assert_eq!(ele.lines().count(), 3);
+        assert_eq!(poly.lines().nth(1), Some("4 1"));
+        assert_eq!(poly.lines().last(), Some("0"));
+    }
+
+    #[test]
+    fn test_export_unsupported_format() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let result = export_mesh(&mesh, Path::new("mesh.xyz"), None, ColorSpace::Srgb);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_write_obj() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let obj = write_to_string(|w| write_obj(&mesh, None, w));
+        let lines: Vec<&str> = obj.lines().collect();
+
+        assert_eq!(lines.iter().filter(|line| line.starts_with("v ")).count(), 4);
+        assert_eq!(lines.iter().filter(|line| line.starts_with("f ")).count(), 2);
+        assert!(lines.contains(&"v 0 0 0 1 0 0"));
+        assert!(lines.contains(&"v 10 -10 0 1 1 1"));
+
+        let by_luminance = Extrusion { source: HeightSource::Luminance, scale: 2.0 };
+        let obj = write_to_string(|w| write_obj(&mesh, Some(&by_luminance), w));
+        assert!(obj.lines().any(|line| line == "v 10 -10 2 1 1 1"));
+    }
+
+    #[test]
+    fn test_write_svg() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let face_colors = [Rgba([1.0, 0.5, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 0.5])];
+        let svg = write_to_string(|w| write_svg(&mesh, 11, 11, &face_colors, w));
+        let lines: Vec<&str> = svg.lines().collect();
+
+        assert!(lines[0].contains("viewBox=\"0 0 11 11\""));
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("fill=\"#ff8000\" stroke=\"#ff8000\""));
+        assert!(lines[2].contains("fill=\"#0000ff\" fill-opacity=\"0.5\""));
+        assert!(lines[1].contains("0.5,0.5") || lines[2].contains("0.5,0.5"));
+        assert_eq!(lines[3], "</svg>");
+
+        // Fully transparent triangles are left out
+        let face_colors = [Rgba([1.0, 0.5, 0.0, 1.0]), Rgba([0.0; 4])];
+        let svg = write_to_string(|w| write_svg(&mesh, 11, 11, &face_colors, w));
+        assert_eq!(svg.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_export_svg_in_color_space() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let path = std::env::temp_dir().join("adaptri_export_test.svg");
+
+        // The triangles are averaged like the rasterizer does in the color space
+        export_mesh(&mesh, &path, None, ColorSpace::Linear).unwrap();
+        let face_colors: Vec<Rgba<f32>> = mesh
+            .triangles()
+            .map(|face| interpolate_triangle_average_color(&face.as_triangle(), ColorSpace::Linear))
+            .collect();
+        let linear = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(linear, write_to_string(|w| write_svg(&mesh, 11, 11, &face_colors, w)));
+
+        export_mesh(&mesh, &path, None, ColorSpace::Srgb).unwrap();
+        assert_ne!(std::fs::read_to_string(&path).unwrap(), linear);
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> usize {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_le_bytes(word) as usize
+    }
+
+    fn write_glb_to_vec(mesh: &Mesh, extrusion: Option<&Extrusion>) -> (serde_json::Value, Vec<u8>) {
+        let mut glb = Vec::new();
+        write_glb(mesh, extrusion, &mut glb).unwrap();
+
+        assert_eq!(&glb[0..4], b"glTF");
+        assert_eq!(read_u32(&glb, 4), 2);
+        assert_eq!(read_u32(&glb, 8), glb.len());
+        assert_eq!(&glb[16..20], b"JSON");
+
+        let json_length = read_u32(&glb, 12);
+        let gltf = serde_json::from_slice(&glb[20..20 + json_length]).unwrap();
+        assert_eq!(&glb[24 + json_length..28 + json_length], b"BIN\0");
+        let bin = glb[28 + json_length..].to_vec();
+        (gltf, bin)
+    }
+
+    #[test]
+    fn test_write_glb() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+        let (gltf, bin) = write_glb_to_vec(&mesh, None);
+
+        assert_eq!(gltf["asset"]["version"], "2.0");
+        assert_eq!(gltf["accessors"][0]["count"], 4);
+        assert_eq!(gltf["accessors"][2]["count"], 6);
+        assert_eq!(gltf["accessors"][0]["min"], json!([0.0, -10.0, 0.0]));
+        assert_eq!(gltf["accessors"][0]["max"], json!([10.0, 0.0, 0.0]));
+        assert_eq!(gltf["buffers"][0]["byteLength"], bin.len());
+        assert_eq!(bin.len(), 4 * 12 + 4 * 12 + 6 * 4);
+    }
+
+    #[test]
+    fn test_write_glb_extruded() {
+        let mesh = create_square_triangulation(10.0, CORNERS);
+
+        let by_luminance = Extrusion { source: HeightSource::Luminance, scale: 2.0 };
+        let (gltf, _) = write_glb_to_vec(&mesh, Some(&by_luminance));
+        assert_eq!(gltf["accessors"][0]["max"][2], 2.0);
+
+        let height_map = GrayImage::from_fn(2, 2, |x, y| Luma([if x == 1 && y == 1 { 255 } else { 0 }]));
+        let by_height_map = Extrusion { source: HeightSource::HeightMap(height_map), scale: 5.0 };
+        let (gltf, bin) = write_glb_to_vec(&mesh, Some(&by_height_map));
+        assert_eq!(gltf["accessors"][0]["min"][2], 0.0);
+        assert_eq!(gltf["accessors"][0]["max"][2], 5.0);
+
+        // Only the vertex at (10, 10) is raised
+        let z_of_last_vertex = f32::from_le_bytes([bin[44], bin[45], bin[46], bin[47]]);
+        assert_eq!(z_of_last_vertex, 5.0);
+    }
+}
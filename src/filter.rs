@@ -0,0 +1,233 @@
+// Standard library imports
+use std::str::FromStr;
+
+// Community library imports
+use image::{Rgba, Rgba32FImage};
+use serde::Serialize;
+
+// Pre-filter applied to the image the refinement compares the mesh with,
+// so that sensor noise and compression artifacts are not refined.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "name")]
+pub enum Filter {
+    // Gaussian blur with the given standard deviation in pixels.
+    Gaussian { sigma: f32 },
+    // Edge preserving blur, weighting the neighbors by their distance in
+    // pixels and by the difference of their colors.
+    Bilateral { sigma_spatial: f32, sigma_range: f32 },
+    // Median of every channel in the square window of the given radius.
+    Median { radius: u32 },
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    // Parses the name of the filter, optionally followed by its parameters,
+    // e.g. `gaussian:1.5`, `bilateral:2,0.1` or `median:1`.
+    fn from_str(s: &str) -> Result<Filter, String> {
+        let (name, parameters) = match s.split_once(':') {
+            Some((name, parameters)) => (name, Some(parameters)),
+            None => (s, None),
+        };
+        let values = match parameters {
+            Some(parameters) => parameters
+                .split(',')
+                .map(|value| value.trim().parse::<f32>().map_err(|_| format!("invalid number '{}' in '{}'", value, s)))
+                .collect::<Result<Vec<f32>, String>>()?,
+            None => Vec::new(),
+        };
+        if values.iter().any(|value| *value <= 0.0) {
+            return Err(format!("the parameters of the filter '{}' must be positive", s));
+        }
+
+        match (name, values.as_slice()) {
+            ("gaussian", []) => Ok(Filter::Gaussian { sigma: 1.0 }),
+            ("gaussian", [sigma]) => Ok(Filter::Gaussian { sigma: *sigma }),
+            ("bilateral", []) => Ok(Filter::Bilateral { sigma_spatial: 2.0, sigma_range: 0.1 }),
+            ("bilateral", [sigma_spatial, sigma_range]) => {
+                Ok(Filter::Bilateral { sigma_spatial: *sigma_spatial, sigma_range: *sigma_range })
+            }
+            ("median", []) => Ok(Filter::Median { radius: 1 }),
+            ("median", [radius]) => Ok(Filter::Median { radius: *radius as u32 }),
+            ("gaussian" | "bilateral" | "median", _) => Err(format!("wrong number of parameters in '{}'", s)),
+            _ => Err(format!("unknown filter '{}'", name)),
+        }
+    }
+}
+
+impl Filter {
+    pub fn apply(&self, img: &Rgba32FImage) -> Rgba32FImage {
+        match *self {
+            Filter::Gaussian { sigma } => gaussian_blur(img, sigma),
+            Filter::Bilateral { sigma_spatial, sigma_range } => bilateral_filter(img, sigma_spatial, sigma_range),
+            Filter::Median { radius } => median_filter(img, radius),
+        }
+    }
+}
+
+// Colors are premultiplied by alpha while filtering, so that the colors of
+// transparent pixels do not bleed in
+fn premultiplied(c: &Rgba<f32>) -> [f32; 4] {
+    [c[0] * c[3], c[1] * c[3], c[2] * c[3], c[3]]
+}
+
+fn unpremultiplied(sum: [f32; 4]) -> Rgba<f32> {
+    if sum[3] > 0.0 {
+        Rgba([sum[0] / sum[3], sum[1] / sum[3], sum[2] / sum[3], sum[3]])
+    } else {
+        Rgba([0.0; 4])
+    }
+}
+
+// Separable blur with a kernel cut off at three standard deviations and
+// clamped to the edges of the image
+fn gaussian_blur(img: &Rgba32FImage, sigma: f32) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let radius = (3.0 * sigma).ceil() as i64;
+    let kernel: Vec<f32> = (-radius..=radius).map(|d| (-((d * d) as f32) / (2.0 * sigma * sigma)).exp()).collect();
+    let total: f32 = kernel.iter().sum();
+
+    let mut horizontal = vec![[0.0f32; 4]; (width * height) as usize];
+    for (col, row, _) in img.enumerate_pixels() {
+        let mut sum = [0.0; 4];
+        for (k, weight) in kernel.iter().enumerate() {
+            let x = (col as i64 + k as i64 - radius).clamp(0, width as i64 - 1) as u32;
+            let c = premultiplied(img.get_pixel(x, row));
+            for i in 0..4 {
+                sum[i] += weight / total * c[i];
+            }
+        }
+        horizontal[(row * width + col) as usize] = sum;
+    }
+
+    Rgba32FImage::from_fn(width, height, |col, row| {
+        let mut sum = [0.0; 4];
+        for (k, weight) in kernel.iter().enumerate() {
+            let y = (row as i64 + k as i64 - radius).clamp(0, height as i64 - 1) as u32;
+            let c = horizontal[(y * width + col) as usize];
+            for i in 0..4 {
+                sum[i] += weight / total * c[i];
+            }
+        }
+        unpremultiplied(sum)
+    })
+}
+
+// Brute force bilateral filter within two spatial standard deviations
+fn bilateral_filter(img: &Rgba32FImage, sigma_spatial: f32, sigma_range: f32) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let radius = (2.0 * sigma_spatial).ceil() as i64;
+
+    Rgba32FImage::from_fn(width, height, |col, row| {
+        let center = premultiplied(img.get_pixel(col, row));
+        let mut sum = [0.0; 4];
+        let mut total = 0.0;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let (x, y) = (col as i64 + dx, row as i64 + dy);
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    continue;
+                }
+                let c = premultiplied(img.get_pixel(x as u32, y as u32));
+                let distance_squared = (dx * dx + dy * dy) as f32;
+                let difference_squared: f32 = (0..4).map(|i| (c[i] - center[i]).powi(2)).sum();
+                let weight = (-distance_squared / (2.0 * sigma_spatial * sigma_spatial)
+                    - difference_squared / (2.0 * sigma_range * sigma_range))
+                    .exp();
+                for i in 0..4 {
+                    sum[i] += weight * c[i];
+                }
+                total += weight;
+            }
+        }
+        unpremultiplied(sum.map(|value| value / total))
+    })
+}
+
+// Median of every channel within the window, clamped to the image
+fn median_filter(img: &Rgba32FImage, radius: u32) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let mut window = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+
+    Rgba32FImage::from_fn(width, height, |col, row| {
+        let mut median = [0.0; 4];
+        for (i, value) in median.iter_mut().enumerate() {
+            window.clear();
+            for y in row.saturating_sub(radius)..(row + radius + 1).min(height) {
+                for x in col.saturating_sub(radius)..(col + radius + 1).min(width) {
+                    window.push(img.get_pixel(x, y)[i]);
+                }
+            }
+            let middle = window.len() / 2;
+            *value = *window.select_nth_unstable_by(middle, |a, b| a.total_cmp(b)).1;
+        }
+        Rgba(median)
+    })
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+
+    // Black left half and white right half with a single black pixel
+    fn create_noisy_edge_image() -> Rgba32FImage {
+        Rgba32FImage::from_fn(10, 10, |x, y| {
+            if x < 5 || (x, y) == (7, 5) {
+                Rgba([0.0, 0.0, 0.0, 1.0])
+            } else {
+                Rgba([1.0, 1.0, 1.0, 1.0])
+            }
+        })
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("gaussian".parse::<Filter>(), Ok(Filter::Gaussian { sigma: 1.0 }));
+        assert_eq!("gaussian:1.5".parse::<Filter>(), Ok(Filter::Gaussian { sigma: 1.5 }));
+        assert_eq!(
+            "bilateral:3,0.2".parse::<Filter>(),
+            Ok(Filter::Bilateral { sigma_spatial: 3.0, sigma_range: 0.2 })
+        );
+        assert_eq!("median:2".parse::<Filter>(), Ok(Filter::Median { radius: 2 }));
+        assert!("bilateral:3".parse::<Filter>().is_err());
+        assert!("gaussian:-1".parse::<Filter>().is_err());
+        assert!("box".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn test_gaussian_blur() {
+        let flat = Rgba32FImage::from_pixel(6, 6, Rgba([0.2, 0.4, 0.6, 1.0]));
+        let blurred = Filter::Gaussian { sigma: 1.0 }.apply(&flat);
+        assert!(blurred.pixels().all(|c| (0..4).all(|i| (c[i] - flat.get_pixel(0, 0)[i]).abs() < 1e-5)));
+
+        let blurred = Filter::Gaussian { sigma: 1.0 }.apply(&create_noisy_edge_image());
+        assert!(blurred.get_pixel(4, 2)[0] > 0.0);
+        assert!(blurred.get_pixel(5, 2)[0] < 1.0);
+    }
+
+    #[test]
+    fn test_bilateral_filter() {
+        let filtered = Filter::Bilateral { sigma_spatial: 2.0, sigma_range: 0.1 }.apply(&create_noisy_edge_image());
+
+        // The edge is preserved, the noise is not smoothed either as it
+        // differs too much from its neighbors
+        assert!(filtered.get_pixel(4, 2)[0] < 1e-3);
+        assert!(filtered.get_pixel(5, 2)[0] > 0.999);
+
+        // Small differences are smoothed
+        let mut img = Rgba32FImage::from_pixel(5, 5, Rgba([0.5, 0.5, 0.5, 1.0]));
+        img.put_pixel(2, 2, Rgba([0.55, 0.55, 0.55, 1.0]));
+        let filtered = Filter::Bilateral { sigma_spatial: 2.0, sigma_range: 0.1 }.apply(&img);
+        assert!(filtered.get_pixel(2, 2)[0] < 0.52);
+    }
+
+    #[test]
+    fn test_median_filter() {
+        let img = create_noisy_edge_image();
+        let filtered = Filter::Median { radius: 1 }.apply(&img);
+
+        assert_eq!(*filtered.get_pixel(7, 5), Rgba([1.0, 1.0, 1.0, 1.0]));
+        assert_eq!(*filtered.get_pixel(4, 2), Rgba([0.0, 0.0, 0.0, 1.0]));
+        assert_eq!(*filtered.get_pixel(5, 2), Rgba([1.0, 1.0, 1.0, 1.0]));
+    }
+}
@@ -0,0 +1,197 @@
+// Community library imports
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+
+// A region of interest in pixel coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    // Given by its top left corner, width and height.
+    Rectangle { x: f32, y: f32, width: f32, height: f32 },
+    // Given by its corners in order.
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Region {
+    // Parses a rectangle written as `x,y,width,height`.
+    pub fn parse_rectangle(s: &str) -> Result<Region, String> {
+        let values = parse_numbers(s)?;
+        if values.len() != 4 || values[2] < 0.0 || values[3] < 0.0 {
+            return Err(format!("invalid rectangle '{}', expected x,y,width,height", s));
+        }
+        Ok(Region::Rectangle { x: values[0], y: values[1], width: values[2], height: values[3] })
+    }
+
+    // Parses a polygon written as `x1,y1,x2,y2,x3,y3,...`.
+    pub fn parse_polygon(s: &str) -> Result<Region, String> {
+        let values = parse_numbers(s)?;
+        if values.len() < 6 || values.len() % 2 != 0 {
+            return Err(format!("invalid polygon '{}', expected at least three x,y pairs", s));
+        }
+        Ok(Region::Polygon(values.chunks(2).map(|xy| (xy[0], xy[1])).collect()))
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Region::Rectangle { x: left, y: top, width, height } => {
+                x >= *left && x < left + width && y >= *top && y < top + height
+            }
+            Region::Polygon(corners) => {
+                // Even-odd rule: count the edges crossed by a ray to the right
+                let mut inside = false;
+                let mut j = corners.len() - 1;
+                for i in 0..corners.len() {
+                    let (xi, yi) = corners[i];
+                    let (xj, yj) = corners[j];
+                    if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+}
+
+fn parse_numbers(s: &str) -> Result<Vec<f32>, String> {
+    s.split(',')
+        .map(|value| value.trim().parse::<f32>().map_err(|_| format!("invalid number '{}' in '{}'", value, s)))
+        .collect()
+}
+
+// How important the details of every pixel are, from 0 for background to
+// 1 for regions of interest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportanceMap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl ImportanceMap {
+    // Takes the importance from a grayscale mask, white being important.
+    // The mask is stretched to the given size if necessary.
+    pub fn from_mask(mask: &GrayImage, width: u32, height: u32) -> ImportanceMap {
+        let resized;
+        let mask = if mask.dimensions() == (width, height) {
+            mask
+        } else {
+            resized = imageops::resize(mask, width, height, FilterType::Triangle);
+            &resized
+        };
+        ImportanceMap { width, height, values: mask.pixels().map(|p| p[0] as f32 / 255.0).collect() }
+    }
+
+    // Pixels inside any of the regions are important, all others are not.
+    pub fn from_regions(regions: &[Region], width: u32, height: u32) -> ImportanceMap {
+        let mut values = vec![0.0; (width * height) as usize];
+        for row in 0..height {
+            for col in 0..width {
+                // Pixels are sampled at their centers
+                let (x, y) = (col as f32 + 0.5, row as f32 + 0.5);
+                if regions.iter().any(|region| region.contains(x, y)) {
+                    values[(row * width + col) as usize] = 1.0;
+                }
+            }
+        }
+        ImportanceMap { width, height, values }
+    }
+
+    // Keeps the higher importance of both maps for every pixel.
+    pub fn combine(mut self, other: &ImportanceMap) -> ImportanceMap {
+        for (value, other) in self.values.iter_mut().zip(other.values.iter()) {
+            *value = value.max(*other);
+        }
+        self
+    }
+
+    // Samples the map at a different resolution, e.g. for the levels of an
+    // image pyramid.
+    pub fn resized(&self, width: u32, height: u32) -> ImportanceMap {
+        let scale_x = self.width as f32 / width as f32;
+        let scale_y = self.height as f32 / height as f32;
+        let mut values = Vec::with_capacity((width * height) as usize);
+        for row in 0..height {
+            for col in 0..width {
+                values.push(self.get((col as f32 + 0.5) * scale_x, (row as f32 + 0.5) * scale_y));
+            }
+        }
+        ImportanceMap { width, height, values }
+    }
+
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let col = (x.max(0.0) as u32).min(self.width - 1);
+        let row = (y.max(0.0) as u32).min(self.height - 1);
+        self.values[(row * self.width + col) as usize]
+    }
+
+    // Factor of the refinement threshold at the position, 1 for important
+    // pixels up to `strength` for unimportant ones.
+    pub fn threshold_scale(&self, x: f32, y: f32, strength: f32) -> f32 {
+        strength.powf(1.0 - self.get(x, y))
+    }
+
+    // Relative density of the initial nodes at the position, 1 for
+    // important pixels down to `1 / strength` for unimportant ones.
+    pub fn seeding_density(&self, x: f32, y: f32, strength: f32) -> f32 {
+        1.0 / self.threshold_scale(x, y, strength)
+    }
+}
+
+#[cfg(test)]
+mod importance_tests {
+    use image::Luma;
+    use super::*;
+
+    #[test]
+    fn test_parse_regions() {
+        assert_eq!(
+            Region::parse_rectangle("1, 2,30,40"),
+            Ok(Region::Rectangle { x: 1.0, y: 2.0, width: 30.0, height: 40.0 })
+        );
+        assert!(Region::parse_rectangle("1,2,3").is_err());
+        assert!(Region::parse_rectangle("1,2,-3,4").is_err());
+
+        assert_eq!(
+            Region::parse_polygon("0,0,10,0,0,10"),
+            Ok(Region::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]))
+        );
+        assert!(Region::parse_polygon("0,0,10,0").is_err());
+        assert!(Region::parse_polygon("0,0,10,0,0,ten").is_err());
+    }
+
+    #[test]
+    fn test_region_contains() {
+        let rectangle = Region::Rectangle { x: 2.0, y: 2.0, width: 4.0, height: 2.0 };
+        assert!(rectangle.contains(2.0, 3.0));
+        assert!(!rectangle.contains(6.0, 3.0));
+        assert!(!rectangle.contains(3.0, 1.0));
+
+        let triangle = Region::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)]);
+        assert!(triangle.contains(2.0, 2.0));
+        assert!(!triangle.contains(6.0, 6.0));
+        assert!(!triangle.contains(-1.0, 2.0));
+    }
+
+    #[test]
+    fn test_importance_map() {
+        let regions = [Region::Rectangle { x: 0.0, y: 0.0, width: 2.0, height: 4.0 }];
+        let map = ImportanceMap::from_regions(&regions, 4, 4);
+        assert_eq!(map.get(1.0, 3.0), 1.0);
+        assert_eq!(map.get(3.0, 3.0), 0.0);
+        assert_eq!(map.threshold_scale(1.0, 3.0, 4.0), 1.0);
+        assert_eq!(map.threshold_scale(3.0, 3.0, 4.0), 4.0);
+        assert_eq!(map.seeding_density(3.0, 3.0, 4.0), 0.25);
+
+        // Masks are stretched to the size of the image
+        let mut mask = GrayImage::new(2, 1);
+        mask.put_pixel(1, 0, Luma([255]));
+        let map = ImportanceMap::from_mask(&mask, 4, 4).combine(&map);
+        assert_eq!(map.get(0.0, 0.0), 1.0);
+        assert_eq!(map.get(3.0, 3.0), 1.0);
+        assert!(map.get(2.0, 0.0) > 0.0 && map.get(2.0, 0.0) < 1.0);
+
+        let map = ImportanceMap::from_regions(&regions, 4, 4).resized(2, 2);
+        assert_eq!(map.values, vec![1.0, 0.0, 1.0, 0.0]);
+    }
+}
@@ -3,63 +3,96 @@ use image::Rgba;
 use spade::delaunay::VertexHandle;
 
 // Crate imports
+use crate::color::ColorSpace;
 use crate::point::Point;
 
-pub fn interpolate_triangle_average_color(triangle: &[VertexHandle<Point>; 3]) -> Rgba<u8> {
-    let mut color = [0.0; 3];
+// Averages the vertex colors in the given color space.
+pub fn interpolate_triangle_average_color(triangle: &[VertexHandle<Point>; 3], space: ColorSpace) -> Rgba<f32> {
+    let colors = working_colors(triangle, space);
+    let weights = alpha_weights(triangle, [1.0; 3]);
+    let mut color = [0.0; 4];
     for i in 0..3 {
-        color[0] += triangle[i].c[0] as f32;
-        color[1] += triangle[i].c[1] as f32;
-        color[2] += triangle[i].c[2] as f32;
-    }
-    Rgba([
-        (color[0] / 3.0) as u8,
-        (color[1] / 3.0) as u8,
-        (color[2] / 3.0) as u8,
-        255,
-    ])
+        color[0] += weights[i] * colors[i][0];
+        color[1] += weights[i] * colors[i][1];
+        color[2] += weights[i] * colors[i][2];
+        color[3] += colors[i][3];
+    }
+    space.encode(Rgba([
+        color[0] / 3.0,
+        color[1] / 3.0,
+        color[2] / 3.0,
+        color[3] / 3.0,
+    ]))
 }
 
-pub fn interpolate_triangle_centroid(triangle: &[VertexHandle<Point>; 3]) -> Point {
+pub fn interpolate_triangle_centroid(triangle: &[VertexHandle<Point>; 3], space: ColorSpace) -> Point {
     let mut x = 0.0;
     let mut y = 0.0;
-    let mut color = [0.0; 3];
-
-    for i in 0..3 {
-        x += triangle[i].x;
-        y += triangle[i].y;
-        color[0] += triangle[i].c[0] as f32;
-        color[1] += triangle[i].c[1] as f32;
-        color[2] += triangle[i].c[2] as f32;
-    }
-    let c = Rgba([
-        (color[0] / 3.0) as u8,
-        (color[1] / 3.0) as u8,
-        (color[2] / 3.0) as u8,
-        255,
-    ]);
+    for vertex in triangle.iter() {
+        x += vertex.x;
+        y += vertex.y;
+    }
 
     Point::new(
         x/3.0,
         y/3.0,
-        c
+        interpolate_triangle_average_color(triangle, space)
     )
 }
 
+// Interpolates the vertex colors at the point in the given color space.
 pub fn interpolate_rgba_in_triangle(
     point: &Point,
     triangle: &[VertexHandle<Point>; 3],
-) -> Rgba<u8> {
+    space: ColorSpace,
+) -> Rgba<f32> {
+    let colors = working_colors(triangle, space);
     let weights = calc_barycentric_interpolation_weights(point, triangle);
+    let color_weights = alpha_weights(triangle, weights);
 
-    let mut color = [0.0; 3];
+    let mut color = [0.0; 4];
     for i in 0..3 {
-        color[0] += weights[i] * (triangle[i].c[0] as f32);
-        color[1] += weights[i] * (triangle[i].c[1] as f32);
-        color[2] += weights[i] * (triangle[i].c[2] as f32);
+        color[0] += color_weights[i] * colors[i][0];
+        color[1] += color_weights[i] * colors[i][1];
+        color[2] += color_weights[i] * colors[i][2];
+        color[3] += weights[i] * colors[i][3];
     }
 
-    Rgba([color[0] as u8, color[1] as u8, color[2] as u8, 255])
+    space.encode(Rgba(color))
+}
+
+// The vertex colors converted into the color space they are mixed in
+fn working_colors(triangle: &[VertexHandle<Point>; 3], space: ColorSpace) -> [Rgba<f32>; 3] {
+    [
+        space.decode(triangle[0].c),
+        space.decode(triangle[1].c),
+        space.decode(triangle[2].c),
+    ]
+}
+
+// Weights of the color channels when mixing the vertex colors with the given
+// weights. The colors are mixed as if premultiplied by alpha, so that the
+// meaningless color of transparent vertices does not bleed into the result.
+// Premultiplying by a common alpha does not change the mix, so the given
+// weights are kept as they are for uniformly (e.g. fully) opaque triangles.
+fn alpha_weights(triangle: &[VertexHandle<Point>; 3], weights: [f32; 3]) -> [f32; 3] {
+    let alpha = [triangle[0].c[3], triangle[1].c[3], triangle[2].c[3]];
+    if alpha[0] == alpha[1] && alpha[1] == alpha[2] {
+        return weights;
+    }
+
+    // Between fully transparent vertices the color does not matter, but it
+    // has to be a number
+    let total_alpha: f32 = (0..3).map(|i| weights[i] * alpha[i]).sum();
+    if total_alpha <= 0.0 {
+        return weights;
+    }
+    let total_weight: f32 = weights.iter().sum();
+    let mut premultiplied = [0.0; 3];
+    for i in 0..3 {
+        premultiplied[i] = total_weight * weights[i] * alpha[i] / total_alpha;
+    }
+    premultiplied
 }
 
 fn calc_barycentric_interpolation_weights(
@@ -81,42 +114,25 @@ fn calc_barycentric_interpolation_weights(
 #[cfg(test)]
 mod interpolation_tests {
     use super::*;
-    use spade::delaunay::{DelaunayTriangulation, FloatDelaunayTriangulation, DelaunayWalkLocate};
-    use spade::kernels::FloatKernel;
-    
-    type Mesh = DelaunayTriangulation<Point, FloatKernel, DelaunayWalkLocate>;
+    use spade::delaunay::FloatDelaunayTriangulation;
+    use crate::test_util::create_simple_triangulation;
 
-    fn create_simple_triangulation() -> Mesh {
-        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
-        delaunay.insert(Point {
-            x: 0.0,
-            y: 0.0,
-            c: Rgba([255, 0, 0, 255]),
-        });
-        delaunay.insert(Point {
-            x: 10.0,
-            y: 0.0,
-            c: Rgba([0, 255, 0, 255]),
-        });
-        delaunay.insert(Point {
-            x: 0.0,
-            y: 10.0,
-            c: Rgba([0, 0, 255, 255]),
-        });
-        delaunay
+    // Colors are compared with a precision of four decimals
+    fn rounded(c: Rgba<f32>) -> String {
+        format!("{:.4?}", c.0)
     }
 
     #[test]
     fn test_calc_barycentric_interpolation_weights() {
         let mesh = create_simple_triangulation();
 
-        let p1 = Point::new(0.0, 0.0, Rgba([255, 0, 0, 255]));
-        let p2 = Point::new(10.0, 0.0, Rgba([0, 255, 0, 255]));
-        let p3 = Point::new(0.0, 10.0, Rgba([0, 0, 255, 255]));
+        let p1 = Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0]));
+        let p2 = Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 1.0]));
+        let p3 = Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 1.0]));
         let p4 = Point::new(
             (p1.x + p2.x + p3.x)/3.0, 
             (p1.y + p2.y + p3.y)/3.0,
-            Rgba([255/3, 255/3, 255/3, 255])    
+            Rgba([1.0/3.0, 1.0/3.0, 1.0/3.0, 1.0])
         );
 
         for face in mesh.triangles() {
@@ -145,9 +161,9 @@ mod interpolation_tests {
         let mesh = create_simple_triangulation();
         for face in mesh.triangles() {
             let triangle = face.as_triangle();
-            let c = interpolate_triangle_average_color(&triangle);
+            let c = interpolate_triangle_average_color(&triangle, ColorSpace::Srgb);
 
-            assert_eq!(c, Rgba([255/3, 255/3, 255/3, 255]));
+            assert_eq!(rounded(c), rounded(Rgba([1.0/3.0, 1.0/3.0, 1.0/3.0, 1.0])));
         }
     }
 
@@ -157,37 +173,139 @@ mod interpolation_tests {
         let mesh = create_simple_triangulation();
         for face in mesh.triangles() {
             let triangle = face.as_triangle();
-            let c = interpolate_triangle_centroid(&triangle);
+            let c = interpolate_triangle_centroid(&triangle, ColorSpace::Srgb);
 
             assert_eq!(c.x, 10.0/3.0);
             assert_eq!(c.y, 10.0/3.0);
-            assert_eq!(c.c, Rgba([255/3, 255/3, 255/3, 255]));
+            assert_eq!(rounded(c.c), rounded(Rgba([1.0/3.0, 1.0/3.0, 1.0/3.0, 1.0])));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_triangle_avg_color_with_alpha() {
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 0.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 0.0])));
+
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+            let c = interpolate_triangle_average_color(&triangle, ColorSpace::Srgb);
+
+            // The colors of the transparent vertices do not bleed in
+            assert_eq!(rounded(c), rounded(Rgba([1.0, 0.0, 0.0, 1.0/3.0])));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_colour_in_triangle_with_alpha() {
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 0.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 1.0])));
+        let p1 = Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 0.0]));
+        let p2 = Point::new(5.0, 0.0, Rgba([1.0, 0.0, 0.0, 0.5]));
+        let p3 = Point::new(0.0, 5.0, Rgba([0.5, 0.0, 0.5, 1.0]));
+
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p1, &triangle, ColorSpace::Srgb)), rounded(p1.c));
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p2, &triangle, ColorSpace::Srgb)), rounded(p2.c));
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p3, &triangle, ColorSpace::Srgb)), rounded(p3.c));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_colour_between_transparent_vertices() {
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 0.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 0.0])));
+        let p = Point::new(5.0, 5.0, Rgba([0.0, 0.5, 0.5, 0.0]));
+
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+            let c = interpolate_rgba_in_triangle(&p, &triangle, ColorSpace::Srgb);
+            assert!(c.0.iter().all(|channel| channel.is_finite()));
+            assert_eq!(rounded(c), rounded(p.c));
         }
     }
 
     #[test]
     fn test_interpolate_colour_in_triangle() {
         let mesh = create_simple_triangulation();
-        let p1 = Point::new(0.0, 0.0, Rgba([255, 0, 0, 255]));
-        let p2 = Point::new(10.0, 0.0, Rgba([0, 255, 0, 255]));
-        let p3 = Point::new(0.0, 10.0, Rgba([0, 0, 255, 255]));
-        let p4 = Point::new(10.0/3.0, 10.0/3.0, Rgba([85, 84, 84, 255]));
-        let p5 = Point::new(0.2, 0.3,Rgba([242, 5, 7, 255]));
+        let p1 = Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0]));
+        let p2 = Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 1.0]));
+        let p3 = Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 1.0]));
+        let p4 = Point::new(10.0/3.0, 10.0/3.0, Rgba([1.0/3.0, 1.0/3.0, 1.0/3.0, 1.0]));
+        let p5 = Point::new(0.2, 0.3, Rgba([0.95, 0.02, 0.03, 1.0]));
 
         for face in mesh.triangles() {
             let triangle = face.as_triangle();
 
-            let c1 = interpolate_rgba_in_triangle(&p1, &triangle);
-            let c2 = interpolate_rgba_in_triangle(&p2, &triangle);
-            let c3 = interpolate_rgba_in_triangle(&p3, &triangle);
-            let c4 = interpolate_rgba_in_triangle(&p4, &triangle);
-            let c5 = interpolate_rgba_in_triangle(&p5, &triangle);
+            let c1 = interpolate_rgba_in_triangle(&p1, &triangle, ColorSpace::Srgb);
+            let c2 = interpolate_rgba_in_triangle(&p2, &triangle, ColorSpace::Srgb);
+            let c3 = interpolate_rgba_in_triangle(&p3, &triangle, ColorSpace::Srgb);
+            let c4 = interpolate_rgba_in_triangle(&p4, &triangle, ColorSpace::Srgb);
+            let c5 = interpolate_rgba_in_triangle(&p5, &triangle, ColorSpace::Srgb);
 
             assert_eq!(c1, p1.c);
             assert_eq!(c2, p2.c);
             assert_eq!(c3, p3.c);
-            assert_eq!(c4, p4.c);
-            assert_eq!(c5, p5.c);
+            assert_eq!(rounded(c4), rounded(p4.c));
+            assert_eq!(rounded(c5), rounded(p5.c));
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_interpolate_in_linear_light() {
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 1.0, 1.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 0.0, 1.0])));
+        let p1 = Point::new(0.0, 0.0, Rgba([1.0, 1.0, 1.0, 1.0]));
+        let p2 = Point::new(5.0, 0.0, Rgba([0.0, 0.0, 0.0, 1.0]));
+
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+
+            // A third of white light is encoded as 0.6125, not as 1/3
+            let c = interpolate_triangle_average_color(&triangle, ColorSpace::Linear);
+            assert_eq!(rounded(c), rounded(Rgba([0.6125, 0.6125, 0.6125, 1.0])));
+
+            // Half of white light is encoded as 0.7354, not as 0.5
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p1, &triangle, ColorSpace::Linear)), rounded(p1.c));
+            let c = interpolate_rgba_in_triangle(&p2, &triangle, ColorSpace::Linear);
+            assert_eq!(rounded(c), rounded(Rgba([0.7354, 0.7354, 0.7354, 1.0])));
+        }
+    }
+
+    #[test]
+    fn test_interpolate_in_perceptual_color_spaces() {
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 1.0, 1.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 0.0, 1.0])));
+        let p = Point::new(5.0, 0.0, Rgba([0.4663, 0.4663, 0.4663, 1.0]));
+
+        // Halfway between black and white is a lightness of 50
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p, &triangle, ColorSpace::Lab)), rounded(p.c));
+        }
+
+        let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
+        delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 1.0])));
+        delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 1.0, 0.0, 1.0])));
+        let p = Point::new(5.0, 0.0, Rgba([1.0, 1.0, 0.5, 1.0]));
+
+        // Red and green mix to a less saturated yellow instead of a dark olive
+        for face in delaunay.triangles() {
+            let triangle = face.as_triangle();
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p, &triangle, ColorSpace::Hsv)), rounded(p.c));
+            assert_eq!(rounded(interpolate_rgba_in_triangle(&p, &triangle, ColorSpace::Srgb)), rounded(Rgba([0.5, 0.5, 0.0, 1.0])));
+        }
+    }
+}
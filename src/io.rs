@@ -3,25 +3,22 @@ use std::path::Path;
 
 // Community library imports
 use image::io::Reader as ImageReader;
-use image::DynamicImage;
-
-pub fn import_image_from_path(path: &Path) -> DynamicImage {
-    let img_reader = match ImageReader::open(path) {
-        Ok(reader) => reader,
-        Err(err) => {
-            eprintln!("ERROR: {}.", err);
-            eprintln!("Exiting program.");
-            std::process::exit(1);
-        }
-    };
-
-    match img_reader.decode() {
-        Ok(img) => img,
-        Err(err) => {
-            eprintln!("ERROR: {}", err);
-            eprintln!("Exiting program.");
-            std::process::exit(1);
+use image::{ColorType, DynamicImage, ImageResult, Rgba32FImage};
+
+pub fn import_image_from_path(path: &Path) -> ImageResult<DynamicImage> {
+    ImageReader::open(path)?.decode()
+}
+
+// Saves the image with the bit depth of the given color type, so that the
+// output keeps the precision of an input image of that type.
+pub fn save_image_with_depth_of(img: Rgba32FImage, color_type: ColorType, path: &Path) -> ImageResult<()> {
+    let img = DynamicImage::ImageRgba32F(img);
+    match color_type {
+        ColorType::Rgb32F | ColorType::Rgba32F => img.save(path),
+        ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => {
+            DynamicImage::ImageRgba16(img.to_rgba16()).save(path)
         }
+        _ => DynamicImage::ImageRgba8(img.to_rgba8()).save(path),
     }
 }
 
@@ -33,6 +30,29 @@ mod io_tests {
     #[test]
     fn test_import_working() {
         let path = PathBuf::from("./data/2017_China_Chongqing_Boats.jpg");
-        let img = import_image_from_path(&path);
+        let img = import_image_from_path(&path).unwrap();
+    }
+
+    #[test]
+    fn test_import_failing() {
+        assert!(import_image_from_path(&PathBuf::from("./data/missing.png")).is_err());
+        assert!(import_image_from_path(&PathBuf::from("./README.md")).is_err());
+    }
+
+    #[test]
+    fn test_save_image_with_depth_of() {
+        let img = Rgba32FImage::from_pixel(2, 2, image::Rgba([1.0 / 65535.0, 0.5, 1.0, 1.0]));
+        let path_8 = std::env::temp_dir().join("adaptri_io_test_8.png");
+        let path_16 = std::env::temp_dir().join("adaptri_io_test_16.png");
+
+        save_image_with_depth_of(img.clone(), ColorType::Rgb8, &path_8).unwrap();
+        save_image_with_depth_of(img, ColorType::Rgba16, &path_16).unwrap();
+
+        let img_8 = import_image_from_path(&path_8).unwrap();
+        let img_16 = import_image_from_path(&path_16).unwrap();
+        assert_eq!(img_8.color(), ColorType::Rgba8);
+        assert_eq!(img_16.color(), ColorType::Rgba16);
+        assert_eq!(img_8.to_rgba16().get_pixel(0, 0)[0], 0);
+        assert_eq!(img_16.to_rgba16().get_pixel(0, 0)[0], 1);
     }
 }
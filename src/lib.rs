@@ -1,135 +1,548 @@
 // Modules
+mod animation;
+mod batch;
 mod boundingbox;
-mod config;
-mod interpolation;
+pub mod cancellation;
+mod cli;
+pub mod color;
+pub mod config;
+mod export;
+mod filter;
+mod importance;
+pub mod interpolation;
 mod io;
-mod point;
-mod rasterization;
-mod refinement;
-mod topology;
+pub mod locate;
+pub mod metrics;
+mod palette;
+pub mod point;
+pub mod progress;
+mod pyramid;
+pub mod rasterization;
+pub mod refinement;
+pub mod report;
+mod serialization;
+mod settings;
+#[cfg(test)]
+mod test_util;
+mod tolerance;
+pub mod topology;
+mod wireframe;
 
 // Standard library imports
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 // Community library imports
-use image::{DynamicImage, GenericImageView};
-use rand::Rng;
-use spade::delaunay::{DelaunayTriangulation, DelaunayWalkLocate, FloatDelaunayTriangulation};
+use image::{DynamicImage, Rgba, Rgba32FImage, RgbaImage};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use spade::delaunay::{DelaunayLocateStructure, DelaunayTreeLocate, DelaunayTriangulation, DelaunayWalkLocate};
 use spade::kernels::FloatKernel;
-use clap::{Arg, App};
+use log::{debug, warn};
 
 // Crate imports
-use crate::config::Config;
-use crate::io::import_image_from_path;
+use crate::animation::save_animation;
+use crate::batch::{output_path, path_for_input, Input};
+use crate::cancellation::CancellationToken;
+use crate::config::{Config, RenderSettings};
+use crate::export::{export_mesh, Extrusion, HeightSource};
+use crate::importance::ImportanceMap;
+use crate::interpolation::interpolate_triangle_average_color;
+use crate::io::{import_image_from_path, save_image_with_depth_of};
+use crate::locate::{insert_points, InsertionOrder, LocateStrategy};
+use crate::metrics::{compare, error_heatmap, QualityMetrics};
+use crate::palette::{load_palette, quantize_faces, Palette};
 use crate::point::Point;
-use crate::refinement::refine_mesh_by_centroid;
+use crate::progress::{NoProgress, Observer, Stage};
+use crate::pyramid::{gaussian_pyramid, split_iterations, upscale_mesh};
+use crate::refinement::{refine_mesh_by_centroid, ChangedFaces, RefinementCriteria};
 use crate::rasterization::rasterize_mesh;
+use crate::report::{save_report, IterationStatistics, MeshStatistics, Report, ReportParameters, StageTiming};
+use crate::serialization::{load_mesh, save_mesh, MeshFile, MeshParameters};
+use crate::tolerance::ToleranceMap;
+use crate::wireframe::{draw_wireframe, WireframeMode};
+
+pub use crate::cli::run;
 
 // Type definitions
-type Mesh = DelaunayTriangulation<Point, FloatKernel, DelaunayWalkLocate>;
+// The mesh of colored points with the given point location, shared by all
+// modules
+pub type Mesh<L = DelaunayWalkLocate> = DelaunayTriangulation<Point, FloatKernel, L>;
+
+// Outcome of triangulizing a single image.
+pub struct Summary {
+    pub path_out: PathBuf,
+    pub n_vertices: usize,
+    pub metrics: QualityMetrics,
+    pub seconds: f64,
+    // Whether the refinement was stopped by the time budget.
+    pub budget_expired: bool,
+}
+
+// Error of a pipeline stopped by its cancellation token
+const CANCELLED: &str = "Cancelled";
+
+// Triangulizes the image at the given path like the `triangulate` subcommand.
+// Cancelling the token stops it with an error, the time budget only stops
+// the refinement.
+pub fn triangulate_image(
+    path: &Path,
+    config: &Config,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Result<Summary, String> {
+    process_image(&Input::file(path.to_path_buf()), false, config, observer, token)
+}
+
+// Triangulizes a single image with the mesh of the configured locate
+// strategy
+fn process_image(
+    input: &Input,
+    batch: bool,
+    config: &Config,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Result<Summary, String> {
+    match config.locate {
+        LocateStrategy::Walk => process_image_with::<DelaunayWalkLocate>(input, batch, config, observer, token),
+        LocateStrategy::Tree => process_image_with::<DelaunayTreeLocate<Point>>(input, batch, config, observer, token),
+    }
+}
 
-// Starting point of command line program
-// TODO: Put this into a separate binary. After all, this should be a library
-pub fn run() {
-    // Step 0: Parsing command line arguments
-    let config = parse_arguments();
+// Triangulizes a single image. The files written next to the output image
+// get the name of the input appended if several images are processed.
+fn process_image_with<L: DelaunayLocateStructure<Point>>(
+    input: &Input,
+    batch: bool,
+    config: &Config,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Result<Summary, String> {
+    let total = Instant::now();
+    let mut timings = Vec::new();
+    let per_input = |path: &PathBuf| if batch { path_for_input(path, input) } else { path.clone() };
+    let path_out = output_path(input, config.dir_out.as_deref());
 
     // Step 1: Importing the image from a given path
-    let img = import_image_from_path(&config.path_in);
+    // The whole pipeline works on floating point colors, so that the output
+    // can be saved with the bit depth of the input
+    let start = Instant::now();
+    let source = import_image_from_path(&input.path).map_err(|err| err.to_string())?;
+    let color_type = source.color();
+    let img = source.to_rgba32f();
+
+    // The mesh is compared with a pre-filtered image if requested, so that
+    // noise and compression artifacts are not refined
+    let filtered = config.prefilter.map(|filter| filter.apply(&img));
+
+    // Coarse to fine refinement starts at the smallest level of a Gaussian
+    // pyramid. A resumed mesh is refined at full resolution only.
+    let n_levels = if config.path_mesh_in.is_some() { 1 } else { config.pyramid_levels };
+    let pyramid = gaussian_pyramid(filtered.as_ref().unwrap_or(&img), n_levels);
+    let coarsest = pyramid.last().unwrap();
+
+    // Regions of interest get more initial nodes and are refined in more
+    // detail than the rest of the image
+    let importance = build_importance_map(config, img.width(), img.height())?;
+    timings.push(StageTiming::since("import", start));
 
     // Step 2: Creating a Mesh of the input image by delaunay triangulation
-    let mut delaunay = delaunay_of_random_image_pixels(&img, config.n_initial_points);
+    // or resuming from a previously saved one
+    let start = Instant::now();
+    let (mut delaunay, previous_parameters): (Mesh<L>, _) = match &config.path_mesh_in {
+        Some(path) => {
+            let (mesh_file, mesh) = load_saved_mesh(path)?;
+            if mesh_file.width != img.width() || mesh_file.height != img.height() {
+                return Err(format!(
+                    "The mesh was created for a {}x{} image, but the input image is {}x{}",
+                    mesh_file.width, mesh_file.height, img.width(), img.height()
+                ));
+            }
+            (mesh, Some(mesh_file.parameters))
+        }
+        None => {
+            observer.stage_started(Stage::Seeding, config.n_initial_points);
+            let mesh = delaunay_of_random_image_pixels(
+                coarsest,
+                config.n_initial_points,
+                config.skip_transparent,
+                importance.as_ref().map(|importance| importance.resized(coarsest.width(), coarsest.height())).as_ref(),
+                config.roi_strength,
+                config.seed,
+                config.insertion_order,
+                observer,
+                token,
+            );
+            observer.stage_finished();
+            (mesh, None)
+        }
+    };
+    if token.is_cancelled() {
+        return Err(CANCELLED.to_string());
+    }
+
+    // Refining towards a palette requires it before the refinement, so it is
+    // generated from the vertices, which are a random sample of the image
+    let mut palette = None;
+    if config.refine_to_palette {
+        if let Some(spec) = &config.render.palette {
+            let colors: Vec<Rgba<f32>> = delaunay.vertices().map(|vertex| vertex.c).filter(|c| c[3] > 0.0).collect();
+            palette = Some(build_palette(spec, &colors, &config.render)?);
+        }
+    }
+
+    timings.push(StageTiming::since("seeding", start));
+
+    // Step 3: Refining the Mesh at every level of the pyramid, capturing a
+    // frame of every state at full resolution if the refinement process is
+    // to be animated. The time budget only covers the refinement, so that
+    // it does not depend on how long the image took to load.
+    let start = Instant::now();
+    let token = match config.time_budget {
+        Some(budget) => token.with_budget(budget),
+        None => token.clone(),
+    };
+    let mut iterations = Vec::new();
+    let mut frames = Vec::new();
+    let capture_frames = config.path_animation.is_some();
+    let n_iterations_per_level = split_iterations(config.n_iterations, pyramid.len());
+    for (level, level_img) in pyramid.iter().enumerate().rev() {
+        let (width, height) = level_img.dimensions();
+        if level + 1 < pyramid.len() {
+            let (previous_width, previous_height) = pyramid[level + 1].dimensions();
+            delaunay = upscale_mesh(&delaunay, previous_width, previous_height, level_img);
+        }
+
+        // Textured regions tolerate larger differences than smooth gradients
+        let tolerance = config
+            .adaptive_strength
+            .map(|strength| ToleranceMap::from_local_contrast(level_img, config.adaptive_radius, strength));
+        let level_importance = importance.as_ref().map(|importance| importance.resized(width, height));
+        let criteria = RefinementCriteria {
+            max_diff: config.max_diff,
+            skip_transparent: config.skip_transparent,
+            space: config.render.color_space,
+            palette: palette.as_ref(),
+            palette_metric: config.render.palette_metric,
+            importance: level_importance.as_ref(),
+            importance_strength: config.roi_strength,
+            tolerance: tolerance.as_ref(),
+        };
+
+        if capture_frames && level + 1 == pyramid.len() {
+            frames.push(render_frame(&delaunay, width, height, &img, palette.as_ref(), config, &token));
+        }
+        let n_iterations = n_iterations_per_level[level];
+        // Once the time budget expired, the remaining levels are only scaled
+        // up to reach full resolution. Every level starts with a new mesh and
+        // new criteria, so all triangles are checked in its first iteration.
+        let mut changed = ChangedFaces::all();
+        for iteration in 0..n_iterations {
+            if token.should_stop() {
+                break;
+            }
+            observer.stage_started(Stage::Refinement { level, iteration }, changed.num_triangles(&delaunay));
+            let inserted = refine_mesh_by_centroid(
+                &mut delaunay,
+                level_img,
+                &criteria,
+                &mut changed,
+                config.insertion_order,
+                observer,
+                &token,
+            );
+            observer.stage_finished();
+            if token.is_cancelled() {
+                return Err(CANCELLED.to_string());
+            }
+            let statistics = IterationStatistics { level, inserted, n_vertices: delaunay.num_vertices() };
+            observer.iteration_finished(&statistics);
+            iterations.push(statistics);
+            if capture_frames {
+                frames.push(render_frame(&delaunay, width, height, &img, palette.as_ref(), config, &token));
+            }
+        }
+    }
+    let budget_expired = token.is_expired();
+    if budget_expired {
+        warn!("{}: the time budget expired, finishing with the mesh refined so far", input.path.display());
+    }
+
+    // The vertices keep their positions but take the colors of the
+    // unfiltered image if requested
+    if config.sample_original && filtered.is_some() {
+        delaunay = upscale_mesh(&delaunay, img.width(), img.height(), &img);
+    }
+
+    timings.push(StageTiming::since("refinement", start));
+
+    // Step 4: Creating an image from the mesh by rasterization, limiting the
+    // colors of the triangles to a palette if requested
+    let start = Instant::now();
+    if palette.is_none() {
+        if let Some(spec) = &config.render.palette {
+            palette = Some(build_palette(spec, &visible_face_colors(&delaunay, &config.render), &config.render)?);
+        }
+    }
+    let face_colors = quantize(&delaunay, palette.as_ref(), &config.render);
+    observer.stage_started(Stage::Rasterization, delaunay.num_triangles());
+    let (width, height) = img.dimensions();
+    let img_out = render_mesh(&delaunay, width, height, &config.render, face_colors.as_deref(), observer, &token);
+    observer.stage_finished();
+    if token.is_cancelled() {
+        return Err(CANCELLED.to_string());
+    }
+    timings.push(StageTiming::since("rendering", start));
+
+    // Step 5: Quantifying how well the mesh approximates the image
+    let start = Instant::now();
+    let metrics = compare(&source, &img_out);
+    if let Some(path) = &config.path_error_map {
+        error_heatmap(&img, &img_out).save(per_input(path)).map_err(|err| err.to_string())?;
+    }
+    timings.push(StageTiming::since("metrics", start));
+
+    // Step 6: Exporting the mesh to scientific mesh and 3D formats
+    let start = Instant::now();
+    let extrusion = build_extrusion(config.height.as_deref(), config.height_scale)?;
+    for path in config.paths_export.iter() {
+        export_mesh(&delaunay, &per_input(path), extrusion.as_ref(), config.render.color_space).map_err(|err| err.to_string())?;
+    }
+
+    timings.push(StageTiming::since("export", start));
 
-    // Step 3: Refining the Mesh
-    for _ in 0..config.n_iterations {
-        refine_mesh_by_centroid(&mut delaunay, &img, config.max_diff);
+    // Step 7: Saving the image, the animation and the mesh, so that it can be
+    // refined or rendered again
+    let start = Instant::now();
+    if let Some(dir) = path_out.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
     }
+    save_image_with_depth_of(img_out, color_type, &path_out).map_err(|err| err.to_string())?;
 
-    // Step 4: Creating an image from the mesh by rasterization
-    let img_out = rasterize_mesh(
-        &delaunay,
-        img.width() as i32,
-        img.height() as i32
-    );
-    img_out.save(config.path_out).unwrap();
+    if let Some(path) = &config.path_animation {
+        save_animation(&frames, config.animation_timing, &per_input(path)).map_err(|err| err.to_string())?;
+    }
+
+    if let Some(path) = &config.path_mesh_out {
+        let parameters = MeshParameters {
+            n_initial_points: previous_parameters
+                .as_ref()
+                .map_or(config.n_initial_points, |p| p.n_initial_points),
+            n_iterations: previous_parameters
+                .as_ref()
+                .map_or(0, |p| p.n_iterations) + config.n_iterations,
+            max_diff: config.max_diff,
+            shading: config.render.shading,
+            color_space: config.render.color_space,
+        };
+        let mesh_file = MeshFile::from_mesh(&delaunay, img.width(), img.height(), parameters);
+        save_mesh(&mesh_file, &per_input(path)).map_err(|err| err.to_string())?;
+    }
+    timings.push(StageTiming::since("saving", start));
+    for timing in timings.iter() {
+        debug!("{}: {} took {:.3} s", input.path.display(), timing.stage, timing.seconds);
+    }
+
+    // Step 8: Reporting the run for tracking it over time
+    if let Some(path) = &config.path_report {
+        let report = Report {
+            parameters: ReportParameters::from_config(config, &input.path, &path_out),
+            seed: config.seed,
+            iterations,
+            mesh: MeshStatistics::of(&delaunay),
+            timings,
+            metrics,
+            budget_expired,
+        };
+        save_report(&report, &per_input(path)).map_err(|err| err.to_string())?;
+    }
+
+    Ok(Summary {
+        path_out,
+        n_vertices: delaunay.num_vertices(),
+        metrics,
+        seconds: total.elapsed().as_secs_f64(),
+        budget_expired,
+    })
+}
+
+// Loads a saved mesh and rebuilds its triangulation, naming the file in
+// errors
+fn load_saved_mesh<L: DelaunayLocateStructure<Point>>(path: &Path) -> Result<(MeshFile, Mesh<L>), String> {
+    let error = |err: std::io::Error| format!("{}: {}", path.display(), err);
+    let mesh_file = load_mesh(path).map_err(error)?;
+    let mesh = mesh_file.to_mesh().map_err(error)?;
+    Ok((mesh_file, mesh))
+}
+
+// Rasterizes the mesh and draws the wireframe on top of the triangles or on
+// a plain background
+fn render_mesh<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: u32,
+    height: u32,
+    settings: &RenderSettings,
+    face_colors: Option<&[Rgba<f32>]>,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Rgba32FImage {
+    let (shading, space) = (settings.shading, settings.color_space);
+    let mut img = match settings.wireframe {
+        Some(WireframeMode::Only) => Rgba32FImage::from_pixel(width, height, settings.background),
+        _ => rasterize_mesh(mesh, width as i32, height as i32, shading, space, face_colors, observer, token),
+    };
+    if settings.wireframe.is_some() {
+        draw_wireframe(&mut img, mesh, &settings.wireframe_style);
+    }
+    img
+}
+
+// Displacement of the vertices in Z by 'luminance' or by a height map file
+fn build_extrusion(height: Option<&str>, scale: f32) -> Result<Option<Extrusion>, String> {
+    Ok(match height {
+        Some("luminance") => Some(Extrusion { source: HeightSource::Luminance, scale }),
+        Some(path) => {
+            let height_map = import_image_from_path(Path::new(path)).map_err(|err| format!("{}: {}", path, err))?;
+            Some(Extrusion { source: HeightSource::HeightMap(height_map.to_luma8()), scale })
+        }
+        None => None,
+    })
+}
+
+// Combines the mask and the regions of interest, if any are given
+fn build_importance_map(config: &Config, width: u32, height: u32) -> Result<Option<ImportanceMap>, String> {
+    let mask = match &config.path_roi_mask {
+        Some(path) => {
+            let mask = import_image_from_path(path).map_err(|err| err.to_string())?;
+            Some(ImportanceMap::from_mask(&mask.to_luma8(), width, height))
+        }
+        None => None,
+    };
+    let regions = if config.roi_regions.is_empty() {
+        None
+    } else {
+        Some(ImportanceMap::from_regions(&config.roi_regions, width, height))
+    };
+
+    Ok(match (mask, regions) {
+        (Some(mask), Some(regions)) => Some(mask.combine(&regions)),
+        (mask, regions) => mask.or(regions),
+    })
 }
 
-fn delaunay_of_random_image_pixels(img: &DynamicImage, n_points: usize) -> Mesh {
+// Generates a palette of the given number of colors from the given colors
+// or loads it from the given file
+fn build_palette(palette: &str, colors: &[Rgba<f32>], settings: &RenderSettings) -> Result<Palette, String> {
+    match palette.parse::<usize>() {
+        Ok(n_colors) => Palette::generate(colors, n_colors, settings.palette_method, settings.palette_metric),
+        Err(_) => load_palette(&PathBuf::from(palette)).map_err(|err| format!("{}: {}", palette, err)),
+    }
+}
+
+// Average colors of the triangles that are not fully transparent, from
+// which a palette is generated
+fn visible_face_colors<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, settings: &RenderSettings) -> Vec<Rgba<f32>> {
+    mesh.triangles()
+        .map(|face| interpolate_triangle_average_color(&face.as_triangle(), settings.color_space))
+        .filter(|c| c[3] > 0.0)
+        .collect()
+}
+
+// The palette colors of the triangles if there is a palette
+fn quantize<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    palette: Option<&Palette>,
+    settings: &RenderSettings,
+) -> Option<Vec<Rgba<f32>>> {
+    palette.map(|palette| {
+        quantize_faces(mesh, palette, settings.color_space, settings.palette_metric, settings.dither)
+    })
+}
+
+// Renders a frame of the animation at the size of the image from a mesh
+// created for an image of the given size, taking the vertex colors from the
+// image unless the mesh already has them. Animation frames are always stored
+// with 8 bits per channel.
+fn render_frame<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: u32,
+    height: u32,
+    img: &Rgba32FImage,
+    palette: Option<&Palette>,
+    config: &Config,
+    token: &CancellationToken,
+) -> RgbaImage {
+    let upscaled;
+    let mesh = if (width, height) == img.dimensions() && (config.prefilter.is_none() || !config.sample_original) {
+        mesh
+    } else {
+        upscaled = upscale_mesh(mesh, width, height, img);
+        &upscaled
+    };
+    let face_colors = quantize(mesh, palette, &config.render);
+    let face_colors = face_colors.as_deref();
+    let frame = render_mesh(mesh, img.width(), img.height(), &config.render, face_colors, &NoProgress, token);
+    DynamicImage::ImageRgba32F(frame).to_rgba8()
+}
+
+// Maximum number of attempts to find a pixel that is accepted as a node
+const MAX_SEEDING_ATTEMPTS: usize = 100;
+
+// Places the nodes at the four corners and at random pixels, following the
+// importance and skipping fully transparent pixels if requested
+#[allow(clippy::too_many_arguments)]
+pub fn delaunay_of_random_image_pixels<L: DelaunayLocateStructure<Point>>(
+    img: &Rgba32FImage,
+    n_points: usize,
+    skip_transparent: bool,
+    importance: Option<&ImportanceMap>,
+    importance_strength: f32,
+    seed: u64,
+    order: InsertionOrder,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Mesh<L> {
     let (width, height) = img.dimensions();
-    let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
-
-    delaunay.insert(Point::new(0.0, 0.0, img.get_pixel(0, 0)));
-    delaunay.insert(Point::new((width-1) as f32, 0.0, img.get_pixel(width-1, 0)));
-    delaunay.insert(Point::new(0.0, (height-1) as f32, img.get_pixel(0, height-1)));
-    delaunay.insert(Point::new((width-1) as f32, (height-1) as f32, img.get_pixel(width-1, height-1)));
-
-    let mut rng = rand::thread_rng();
-    for _ in 0..(n_points-4) {
-        let rnd_x = rng.gen::<f32>() * (width as f32);
-        let rnd_y = rng.gen::<f32>() * (height as f32);
-
-        delaunay.insert(Point {
-            x: rnd_x,
-            y: rnd_y,
-            c: img.get_pixel(rnd_x as u32, rnd_y as u32),
+    let mut delaunay = Mesh::new();
+
+    delaunay.insert(Point::new(0.0, 0.0, *img.get_pixel(0, 0)));
+    delaunay.insert(Point::new((width-1) as f32, 0.0, *img.get_pixel(width-1, 0)));
+    delaunay.insert(Point::new(0.0, (height-1) as f32, *img.get_pixel(0, height-1)));
+    delaunay.insert(Point::new((width-1) as f32, (height-1) as f32, *img.get_pixel(width-1, height-1)));
+    observer.advanced(4);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut points = Vec::new();
+    let mut n_missed = 0;
+    for _ in 0..n_points.saturating_sub(4) {
+        if token.should_stop() {
+            break;
+        }
+        observer.advanced(1);
+        let point = (0..MAX_SEEDING_ATTEMPTS).find_map(|_| {
+            let rnd_x = rng.gen::<f32>() * (width as f32);
+            let rnd_y = rng.gen::<f32>() * (height as f32);
+            let c = *img.get_pixel(rnd_x as u32, rnd_y as u32);
+
+            let visible = !skip_transparent || c[3] > 0.0;
+            let important = match importance {
+                Some(importance) => rng.gen::<f32>() < importance.seeding_density(rnd_x, rnd_y, importance_strength),
+                None => true,
+            };
+            if visible && important { Some(Point { x: rnd_x, y: rnd_y, c }) } else { None }
         });
+        match point {
+            Some(point) => points.push(point),
+            None => n_missed += 1,
+        }
+    }
+    if n_missed > 0 {
+        warn!(
+            "Only {} of {} initial nodes were seeded, as no visible or important pixel was found for the others",
+            n_points - n_missed, n_points
+        );
     }
+    insert_points(&mut delaunay, points, order);
     delaunay
 }
-
-fn parse_arguments() -> Config {
-    let matches = App::new("trimage")
-        .version("1.0")
-        .author("Paz Vi <paz@twowaysix.com>")
-        .about("AdapTri - Adaptive Triangulizer")
-        .arg(Arg::with_name("file")
-            .short("f")
-            .long("file")
-            .help("The input image path.")
-            .takes_value(true)
-            .required(true))
-        .arg(Arg::with_name("points")
-            .short("p")
-            .long("points")
-            .help("Number of initial mesh nodes.")
-            .takes_value(true)
-            .default_value("100"))
-        .arg(Arg::with_name("iterations")
-            .short("i")
-            .long("iterations")
-            .help("Number of iterations.")
-            .takes_value(true)
-            .default_value("4"))
-        .arg(Arg::with_name("max_diff")
-            .short("d")
-            .long("max_diff")
-            .help("Maximum difference for no refinement.")
-            .takes_value(true)
-            .default_value("15"))
-        .get_matches();
-
-    let path_in = PathBuf::from(matches.value_of("file").unwrap());
-    let path_out = path_in.parent().unwrap().join(
-        PathBuf::from(
-            format!(
-                "{}_triangulized.{}",
-                path_in.file_stem().unwrap().to_str().unwrap(),
-                path_in.extension().unwrap().to_str().unwrap()
-            )
-        )
-    );
-
-    // TODO: Handle wrong input better
-    let n_initial_points =matches.value_of("points").unwrap().parse::<usize>().unwrap_or(100);
-    let n_iterations = matches.value_of("iterations").unwrap().parse::<i32>().unwrap_or(4);
-    let max_diff = matches.value_of("max_diff").unwrap().parse::<i32>().unwrap_or(15);
-
-    println!("\nChosen parameters:");
-    println!("- Input path:       {}", path_in.to_str().unwrap());
-    println!("- Out path:         {}", path_out.to_str().unwrap());
-    println!("- Initial nodes:    {}", n_initial_points);
-    println!("- Iterations:       {}", n_iterations);
-    println!("- Max. difference:  {}", max_diff);
-
-    Config { path_in, path_out, n_initial_points, n_iterations, max_diff }
-}
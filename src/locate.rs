@@ -0,0 +1,196 @@
+// Standard library imports
+use std::str::FromStr;
+
+// Community library imports
+use serde::Serialize;
+use spade::delaunay::{DelaunayLocateStructure, FixedVertexHandle};
+
+// Crate imports
+use crate::point::Point;
+use crate::Mesh;
+
+// How the triangle containing a new point is found when it is inserted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocateStrategy {
+    // Walks through the mesh from the last inserted point, which is fast
+    // if consecutive points are close to each other.
+    Walk,
+    // Looks up the nearest vertex in an r-tree and walks from there, which
+    // takes logarithmic time regardless of the order of the points but has to
+    // keep the tree up to date.
+    Tree,
+}
+
+impl FromStr for LocateStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LocateStrategy, String> {
+        match s {
+            "walk" => Ok(LocateStrategy::Walk),
+            "tree" => Ok(LocateStrategy::Tree),
+            _ => Err(format!("unknown locate strategy '{}'", s)),
+        }
+    }
+}
+
+// Order in which a batch of points is inserted into the mesh.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InsertionOrder {
+    // The order in which the points were found.
+    Unsorted,
+    // Along a Hilbert curve, whose consecutive points are always close.
+    Hilbert,
+    // Along a Morton (Z-order) curve, which is cheaper to compute but jumps
+    // between the quadrants.
+    Morton,
+}
+
+impl FromStr for InsertionOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<InsertionOrder, String> {
+        match s {
+            "unsorted" => Ok(InsertionOrder::Unsorted),
+            "hilbert" => Ok(InsertionOrder::Hilbert),
+            "morton" => Ok(InsertionOrder::Morton),
+            _ => Err(format!("unknown insertion order '{}'", s)),
+        }
+    }
+}
+
+// Number of cells of the grid the points are snapped to along each axis,
+// as a power of two
+const CURVE_ORDER: u32 = 16;
+
+// Sorts the points along the curve of the given order, spanning their
+// bounding box.
+pub fn sort_points(points: &mut [Point], order: InsertionOrder) {
+    let curve_index: fn(u32, u32) -> u64 = match order {
+        InsertionOrder::Unsorted => return,
+        InsertionOrder::Hilbert => hilbert_index,
+        InsertionOrder::Morton => morton_index,
+    };
+    if points.is_empty() {
+        return;
+    }
+
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+    for point in points.iter() {
+        min_x = min_x.min(point.x);
+        min_y = min_y.min(point.y);
+        max_x = max_x.max(point.x);
+        max_y = max_y.max(point.y);
+    }
+    let n_cells = ((1u32 << CURVE_ORDER) - 1) as f32;
+    let scale = n_cells / (max_x - min_x).max(max_y - min_y).max(f32::EPSILON);
+    points.sort_by_cached_key(|point| {
+        curve_index(((point.x - min_x) * scale) as u32, ((point.y - min_y) * scale) as u32)
+    });
+}
+
+// Inserts the points in the given order and returns their handles in that
+// order.
+pub fn insert_points<L: DelaunayLocateStructure<Point>>(
+    mesh: &mut Mesh<L>,
+    mut points: Vec<Point>,
+    order: InsertionOrder,
+) -> Vec<FixedVertexHandle> {
+    sort_points(&mut points, order);
+    points.into_iter().map(|point| mesh.insert(point)).collect()
+}
+
+// Position of the cell along the Hilbert curve through the grid
+fn hilbert_index(mut x: u32, mut y: u32) -> u64 {
+    let n = 1u32 << CURVE_ORDER;
+    let mut index = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = u32::from(x & s > 0);
+        let ry = u32::from(y & s > 0);
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        // Rotates the quadrant, so that the curve continues where it ended
+        if ry == 0 {
+            if rx == 1 {
+                x = n - 1 - x;
+                y = n - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+// Position of the cell along the Morton curve, interleaving the bits of the
+// coordinates
+fn morton_index(x: u32, y: u32) -> u64 {
+    let spread = |value: u32| {
+        let mut value = value as u64;
+        value = (value | (value << 16)) & 0x0000_ffff_0000_ffff;
+        value = (value | (value << 8)) & 0x00ff_00ff_00ff_00ff;
+        value = (value | (value << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+        value = (value | (value << 2)) & 0x3333_3333_3333_3333;
+        (value | (value << 1)) & 0x5555_5555_5555_5555
+    };
+    spread(x) | (spread(y) << 1)
+}
+
+#[cfg(test)]
+mod locate_tests {
+    use image::Rgba;
+    use spade::delaunay::{DelaunayTreeLocate, DelaunayWalkLocate};
+    use super::*;
+
+    #[test]
+    fn test_hilbert_index() {
+        // Consecutive cells along the curve are neighbors
+        let mut cells: Vec<(u32, u32)> = (0..8).flat_map(|x| (0..8).map(move |y| (x, y))).collect();
+        cells.sort_by_key(|&(x, y)| hilbert_index(x, y));
+        for (i, window) in cells.windows(2).enumerate() {
+            assert_eq!(hilbert_index(window[0].0, window[0].1), i as u64);
+            assert_eq!(window[0].0.abs_diff(window[1].0) + window[0].1.abs_diff(window[1].1), 1);
+        }
+    }
+
+    #[test]
+    fn test_morton_index() {
+        assert_eq!(morton_index(0, 0), 0);
+        assert_eq!(morton_index(1, 0), 1);
+        assert_eq!(morton_index(0, 1), 2);
+        assert_eq!(morton_index(3, 3), 15);
+        assert_eq!(morton_index(0, 4), 32);
+    }
+
+    #[test]
+    fn test_sort_points() {
+        let c = Rgba([0.0; 4]);
+        let mut points = vec![Point::new(9.0, 0.0, c), Point::new(0.0, 9.0, c), Point::new(0.0, 0.0, c)];
+        sort_points(&mut points, InsertionOrder::Unsorted);
+        assert_eq!(points[0].x, 9.0);
+
+        sort_points(&mut points, InsertionOrder::Hilbert);
+        let positions: Vec<(f32, f32)> = points.iter().map(|point| (point.x, point.y)).collect();
+        assert_eq!(positions, vec![(0.0, 0.0), (0.0, 9.0), (9.0, 0.0)]);
+
+        sort_points(&mut points, InsertionOrder::Morton);
+        let positions: Vec<(f32, f32)> = points.iter().map(|point| (point.x, point.y)).collect();
+        assert_eq!(positions, vec![(0.0, 0.0), (9.0, 0.0), (0.0, 9.0)]);
+    }
+
+    #[test]
+    fn test_insert_points() {
+        let points: Vec<Point> = (0..100)
+            .map(|i| Point::new((i * 37 % 100) as f32, (i * 61 % 100) as f32, Rgba([0.0; 4])))
+            .collect();
+        let mut walk = Mesh::<DelaunayWalkLocate>::new();
+        let mut tree = Mesh::<DelaunayTreeLocate<Point>>::new();
+        let handles = insert_points(&mut walk, points.clone(), InsertionOrder::Hilbert);
+        insert_points(&mut tree, points, InsertionOrder::Morton);
+
+        assert_eq!(handles.len(), 100);
+        assert_eq!(walk.num_vertices(), 100);
+        assert_eq!(walk.num_triangles(), tree.num_triangles());
+    }
+}
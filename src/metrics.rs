@@ -0,0 +1,258 @@
+// Community library imports
+use image::{DynamicImage, Rgb, RgbImage, Rgba32FImage};
+use serde::{Serialize, Serializer};
+
+// Stabilizing constants of SSIM for a dynamic range of 1
+const C1: f64 = 0.01 * 0.01;
+const C2: f64 = 0.03 * 0.03;
+
+// Standard deviation of the Gaussian window of SSIM in pixels
+const SSIM_SIGMA: f64 = 1.5;
+
+// Weights of the scales of MS-SSIM from the finest to the coarsest, as
+// published by Wang, Simoncelli and Bovik
+const MS_SSIM_WEIGHTS: [f64; 5] = [0.0448, 0.2856, 0.3001, 0.2363, 0.1333];
+
+// Scales smaller than the Gaussian window of SSIM are not evaluated
+const MIN_SCALE_SIZE: u32 = 11;
+
+// How well an image approximates another one.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct QualityMetrics {
+    // Mean squared error of the premultiplied channels between 0 and 1.
+    pub mse: f64,
+    // Peak signal to noise ratio in dB, infinite for identical images.
+    // JSON has no infinity, so it is serialized as the string `"inf"`.
+    #[serde(serialize_with = "serialize_psnr")]
+    pub psnr: f64,
+    // Structural similarity of the luminance, 1 for identical images.
+    pub ssim: f64,
+    // Structural similarity over up to five scales.
+    pub ms_ssim: f64,
+}
+
+// Compares the output of the rasterization with the source image, which
+// must have the same size.
+pub fn compare(source: &DynamicImage, output: &Rgba32FImage) -> QualityMetrics {
+    let source = source.to_rgba32f();
+    let mse = mse(&source, output);
+    QualityMetrics { mse, psnr: psnr(mse), ssim: ssim(&source, output), ms_ssim: ms_ssim(&source, output) }
+}
+
+fn serialize_psnr<S: Serializer>(psnr: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    if psnr.is_infinite() {
+        serializer.serialize_str("inf")
+    } else {
+        serializer.serialize_f64(*psnr)
+    }
+}
+
+// Colors are premultiplied by alpha, so that the colors of transparent
+// pixels do not count
+fn premultiplied(img: &Rgba32FImage) -> Vec<[f64; 4]> {
+    img.pixels()
+        .map(|c| {
+            let alpha = c[3] as f64;
+            [c[0] as f64 * alpha, c[1] as f64 * alpha, c[2] as f64 * alpha, alpha]
+        })
+        .collect()
+}
+
+pub fn mse(a: &Rgba32FImage, b: &Rgba32FImage) -> f64 {
+    let sum: f64 = premultiplied(a)
+        .iter()
+        .zip(premultiplied(b).iter())
+        .map(|(a, b)| (0..4).map(|i| (a[i] - b[i]).powi(2)).sum::<f64>())
+        .sum();
+    sum / (4 * a.width() * a.height()) as f64
+}
+
+pub fn psnr(mse: f64) -> f64 {
+    10.0 * (1.0 / mse).log10()
+}
+
+// Mean structural similarity of the luminance within a Gaussian window.
+pub fn ssim(a: &Rgba32FImage, b: &Rgba32FImage) -> f64 {
+    let (width, height) = a.dimensions();
+    let (ssim, _) = ssim_components(&luminance(a), &luminance(b), width, height);
+    ssim
+}
+
+// Structural similarity combined over up to five scales, each half the
+// size of the previous one.
+pub fn ms_ssim(a: &Rgba32FImage, b: &Rgba32FImage) -> f64 {
+    let (mut width, mut height) = a.dimensions();
+    let (mut a, mut b) = (luminance(a), luminance(b));
+
+    let mut n_scales = 1;
+    while n_scales < MS_SSIM_WEIGHTS.len() && width >> n_scales >= MIN_SCALE_SIZE && height >> n_scales >= MIN_SCALE_SIZE {
+        n_scales += 1;
+    }
+    // The weights of the evaluated scales are normalized to sum up to one
+    let total: f64 = MS_SSIM_WEIGHTS[..n_scales].iter().sum();
+
+    let mut result = 1.0;
+    for (scale, weight) in MS_SSIM_WEIGHTS[..n_scales].iter().enumerate() {
+        let (ssim, contrast_structure) = ssim_components(&a, &b, width, height);
+        if scale + 1 == n_scales {
+            result *= ssim.max(0.0).powf(weight / total);
+        } else {
+            result *= contrast_structure.max(0.0).powf(weight / total);
+            a = halve(&a, width, height);
+            b = halve(&b, width, height);
+            width /= 2;
+            height /= 2;
+        }
+    }
+    result
+}
+
+// Colors every pixel by the largest difference of its premultiplied
+// channels, from black over red and yellow to white.
+pub fn error_heatmap(a: &Rgba32FImage, b: &Rgba32FImage) -> RgbImage {
+    let (a_values, b_values) = (premultiplied(a), premultiplied(b));
+    RgbImage::from_fn(a.width(), a.height(), |x, y| {
+        let i = (y * a.width() + x) as usize;
+        let error = (0..4).map(|k| (a_values[i][k] - b_values[i][k]).abs()).fold(0.0, f64::max);
+        let ramp = |start: f64| (((error * 3.0 - start).clamp(0.0, 1.0)) * 255.0).round() as u8;
+        Rgb([ramp(0.0), ramp(1.0), ramp(2.0)])
+    })
+}
+
+fn luminance(img: &Rgba32FImage) -> Vec<f64> {
+    premultiplied(img).iter().map(|c| 0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]).collect()
+}
+
+// Mean SSIM and mean contrast-structure term
+fn ssim_components(a: &[f64], b: &[f64], width: u32, height: u32) -> (f64, f64) {
+    let product = |x: &[f64], y: &[f64]| x.iter().zip(y.iter()).map(|(x, y)| x * y).collect::<Vec<f64>>();
+    let mean_a = blur(a, width, height);
+    let mean_b = blur(b, width, height);
+    let mean_aa = blur(&product(a, a), width, height);
+    let mean_bb = blur(&product(b, b), width, height);
+    let mean_ab = blur(&product(a, b), width, height);
+
+    let (mut ssim, mut contrast_structure) = (0.0, 0.0);
+    for i in 0..a.len() {
+        let variance_a = mean_aa[i] - mean_a[i] * mean_a[i];
+        let variance_b = mean_bb[i] - mean_b[i] * mean_b[i];
+        let covariance = mean_ab[i] - mean_a[i] * mean_b[i];
+        let luminance = (2.0 * mean_a[i] * mean_b[i] + C1) / (mean_a[i] * mean_a[i] + mean_b[i] * mean_b[i] + C1);
+        let cs = (2.0 * covariance + C2) / (variance_a + variance_b + C2);
+        ssim += luminance * cs;
+        contrast_structure += cs;
+    }
+    (ssim / a.len() as f64, contrast_structure / a.len() as f64)
+}
+
+// Separable Gaussian blur clamped to the edges
+fn blur(values: &[f64], width: u32, height: u32) -> Vec<f64> {
+    let (w, h) = (width as i64, height as i64);
+    let radius = (3.0 * SSIM_SIGMA).ceil() as i64;
+    let kernel: Vec<f64> = (-radius..=radius).map(|d| (-((d * d) as f64) / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp()).collect();
+    let total: f64 = kernel.iter().sum();
+
+    let mut horizontal = vec![0.0; values.len()];
+    let mut blurred = vec![0.0; values.len()];
+    for y in 0..h {
+        for x in 0..w {
+            horizontal[(y * w + x) as usize] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| weight * values[(y * w + (x + k as i64 - radius).clamp(0, w - 1)) as usize])
+                .sum::<f64>()
+                / total;
+        }
+    }
+    for y in 0..h {
+        for x in 0..w {
+            blurred[(y * w + x) as usize] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, weight)| weight * horizontal[((y + k as i64 - radius).clamp(0, h - 1) * w + x) as usize])
+                .sum::<f64>()
+                / total;
+        }
+    }
+    blurred
+}
+
+// Averages blocks of 2x2 values, dropping an odd last row or column
+fn halve(values: &[f64], width: u32, height: u32) -> Vec<f64> {
+    let (half_width, half_height) = (width / 2, height / 2);
+    let mut halved = Vec::with_capacity((half_width * half_height) as usize);
+    for y in 0..half_height {
+        for x in 0..half_width {
+            let i = (2 * y * width + 2 * x) as usize;
+            let w = width as usize;
+            halved.push((values[i] + values[i + 1] + values[i + w] + values[i + w + 1]) / 4.0);
+        }
+    }
+    halved
+}
+
+#[cfg(test)]
+mod metrics_tests {
+    use image::Rgba;
+    use super::*;
+
+    // Smooth gradient, large enough for several scales of MS-SSIM
+    fn create_gradient_image() -> Rgba32FImage {
+        Rgba32FImage::from_fn(64, 48, |x, y| Rgba([x as f32 / 63.0, y as f32 / 47.0, 0.5, 1.0]))
+    }
+
+    #[test]
+    fn test_identical_images() {
+        let img = create_gradient_image();
+        let metrics = compare(&DynamicImage::ImageRgba32F(img.clone()), &img);
+
+        assert_eq!(metrics.mse, 0.0);
+        assert_eq!(metrics.psnr, f64::INFINITY);
+        assert!((metrics.ssim - 1.0).abs() < 1e-9);
+        assert!((metrics.ms_ssim - 1.0).abs() < 1e-9);
+        assert!(error_heatmap(&img, &img).pixels().all(|c| *c == Rgb([0, 0, 0])));
+
+        // JSON has no infinity, which would be written as null
+        let json = serde_json::to_value(metrics).unwrap();
+        assert_eq!(json["psnr"], "inf");
+        assert_eq!(json["mse"], 0.0);
+    }
+
+    #[test]
+    fn test_mse_and_psnr() {
+        let a = Rgba32FImage::from_pixel(4, 4, Rgba([0.5, 0.5, 0.5, 1.0]));
+        let b = Rgba32FImage::from_pixel(4, 4, Rgba([0.25, 0.5, 0.5, 1.0]));
+
+        // One of four channels differs by 0.25
+        assert_eq!(mse(&a, &b), 0.0625 / 4.0);
+        assert!((psnr(0.01) - 20.0).abs() < 1e-9);
+
+        // Colors of fully transparent pixels do not count
+        let c = Rgba32FImage::from_pixel(4, 4, Rgba([1.0, 0.0, 0.0, 0.0]));
+        let d = Rgba32FImage::from_pixel(4, 4, Rgba([0.0, 1.0, 0.0, 0.0]));
+        assert_eq!(mse(&c, &d), 0.0);
+    }
+
+    #[test]
+    fn test_structural_similarity() {
+        let img = create_gradient_image();
+        let add_noise = |amplitude: f32| {
+            Rgba32FImage::from_fn(64, 48, |x, y| {
+                let c = img.get_pixel(x, y);
+                let noise = if (x + y) % 2 == 0 { amplitude } else { -amplitude };
+                Rgba([c[0] + noise, c[1] + noise, c[2] + noise, 1.0])
+            })
+        };
+        let noisy = add_noise(0.1);
+
+        // Stronger noise destroys more of the structure
+        let ssim_noisy = ssim(&img, &noisy);
+        assert!(ssim_noisy < ssim(&img, &add_noise(0.01)));
+        assert!(ssim(&img, &add_noise(0.01)) < 1.0);
+        assert!(ms_ssim(&img, &noisy) < ms_ssim(&img, &add_noise(0.01)));
+
+        // Small errors are shown in dark red
+        let pixel = *error_heatmap(&img, &noisy).get_pixel(0, 0);
+        assert!(pixel[0] > 0 && pixel[0] < 255 && pixel[1] == 0);
+    }
+}
@@ -0,0 +1,413 @@
+// Standard library imports
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// Community library imports
+use image::Rgba;
+use spade::delaunay::{DelaunayLocateStructure, FixedFaceHandle};
+
+// Crate imports
+use crate::color::{parse_hex_color, ColorSpace};
+use crate::interpolation::{interpolate_triangle_average_color, interpolate_triangle_centroid};
+use crate::point::Point;
+use crate::Mesh;
+
+// Upper limit of k-means iterations if the clusters do not settle earlier
+const MAX_KMEANS_ITERATIONS: usize = 20;
+
+// How a palette is generated from the colors of a mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QuantizationMethod {
+    // Recursively splits the colors at the median of their widest channel.
+    MedianCut,
+    // Refines the median cut palette by k-means clustering.
+    KMeans,
+}
+
+impl FromStr for QuantizationMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<QuantizationMethod, String> {
+        match s {
+            "median-cut" => Ok(QuantizationMethod::MedianCut),
+            "kmeans" => Ok(QuantizationMethod::KMeans),
+            _ => Err(format!("unknown quantization method '{}'", s)),
+        }
+    }
+}
+
+// A list of opaque, sRGB encoded colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<Rgba<f32>>,
+}
+
+impl Palette {
+    // Generates a palette of at most `n_colors` colors, which are clustered
+    // in the color space of the metric.
+    pub fn generate(
+        colors: &[Rgba<f32>],
+        n_colors: usize,
+        method: QuantizationMethod,
+        metric: ColorSpace,
+    ) -> Result<Palette, String> {
+        if colors.is_empty() {
+            return Err("There are no visible colors to generate a palette from".to_string());
+        }
+
+        let values: Vec<[f32; 3]> = colors.iter().map(|c| working_values(*c, metric)).collect();
+        let mut centers = median_cut(&values, n_colors);
+        if method == QuantizationMethod::KMeans {
+            k_means(&values, &mut centers);
+        }
+
+        Ok(Palette {
+            colors: centers
+                .iter()
+                .map(|center| metric.encode(Rgba([center[0], center[1], center[2], 1.0])))
+                .collect(),
+        })
+    }
+
+    // The palette entry closest to the color under the metric, with the
+    // alpha of the color.
+    pub fn nearest(&self, c: Rgba<f32>, metric: ColorSpace) -> Rgba<f32> {
+        let entries = self.working_values(metric);
+        match nearest_index(&entries, working_values(c, metric)) {
+            Some(entry) => {
+                let entry = self.colors[entry];
+                Rgba([entry[0], entry[1], entry[2], c[3]])
+            }
+            // An empty palette leaves the color as it is
+            None => c,
+        }
+    }
+
+    fn working_values(&self, metric: ColorSpace) -> Vec<[f32; 3]> {
+        self.colors.iter().map(|c| working_values(*c, metric)).collect()
+    }
+}
+
+// Loads a GIMP palette (`.gpl`) or a list of hex colors separated by
+// whitespace.
+pub fn load_palette(path: &Path) -> io::Result<Palette> {
+    let text = fs::read_to_string(path)?;
+    let palette = if text.trim_start().starts_with("GIMP Palette") {
+        parse_gpl(&text)
+    } else {
+        parse_hex_list(&text)
+    };
+
+    match palette {
+        Ok(palette) if palette.colors.is_empty() => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} contains no colors", path.display()),
+        )),
+        Ok(palette) => Ok(palette),
+        Err(msg) => Err(io::Error::new(io::ErrorKind::InvalidData, msg)),
+    }
+}
+
+fn parse_gpl(text: &str) -> Result<Palette, String> {
+    let mut colors = Vec::new();
+    for line in text.lines().skip(1) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("Name:") || line.starts_with("Columns:") {
+            continue;
+        }
+
+        let channels: Vec<&str> = line.split_whitespace().take(3).collect();
+        if channels.len() < 3 {
+            return Err(format!("invalid palette entry '{}'", line));
+        }
+        let mut c = [1.0; 4];
+        for (channel, value) in c.iter_mut().zip(channels.iter()) {
+            *channel = match value.parse::<u8>() {
+                Ok(value) => value as f32 / 255.0,
+                Err(_) => return Err(format!("invalid palette entry '{}'", line)),
+            };
+        }
+        colors.push(Rgba(c));
+    }
+    Ok(Palette { colors })
+}
+
+fn parse_hex_list(text: &str) -> Result<Palette, String> {
+    let colors = text
+        .split_whitespace()
+        .map(|token| parse_hex_color(token).map(|c| Rgba([c[0], c[1], c[2], 1.0])))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Palette { colors })
+}
+
+// Maps the average color of every triangle, in the order of
+// `mesh.triangles()`, to its nearest palette entry.
+pub fn quantize_faces<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    palette: &Palette,
+    space: ColorSpace,
+    metric: ColorSpace,
+    dither: bool,
+) -> Vec<Rgba<f32>> {
+    let colors: Vec<Rgba<f32>> = mesh
+        .triangles()
+        .map(|face| interpolate_triangle_average_color(&face.as_triangle(), space))
+        .collect();
+    if !dither || palette.colors.is_empty() {
+        return colors.iter().map(|c| palette.nearest(*c, metric)).collect();
+    }
+
+    // Positions of the faces in `mesh.triangles()`, by which the colors are
+    // indexed
+    let faces: Vec<_> = mesh.triangles().collect();
+    let positions: HashMap<FixedFaceHandle, usize> =
+        faces.iter().enumerate().map(|(index, face)| (face.fix(), index)).collect();
+
+    let entries = palette.working_values(metric);
+    let mut order: Vec<(f32, f32, usize)> = faces
+        .iter()
+        .enumerate()
+        .map(|(index, face)| {
+            let centroid = interpolate_triangle_centroid(&face.as_triangle(), space);
+            (centroid.y, centroid.x, index)
+        })
+        .collect();
+    order.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut errors = vec![[0.0f32; 3]; colors.len()];
+    let mut visited = vec![false; colors.len()];
+    let mut quantized = colors.clone();
+    for (_, _, index) in order {
+        visited[index] = true;
+        let c = colors[index];
+        if c[3] == 0.0 {
+            continue;
+        }
+
+        // The accumulated error is kept within the gamut, otherwise it can
+        // build up in regions the palette cannot reproduce
+        let mut desired = working_values(c, metric);
+        for (value, error) in desired.iter_mut().zip(errors[index].iter()) {
+            *value += error;
+        }
+        let clipped = metric.encode(Rgba([desired[0], desired[1], desired[2], 1.0]));
+        let desired = working_values(Rgba(clipped.0.map(|c| c.clamp(0.0, 1.0))), metric);
+        let entry = nearest_index(&entries, desired).unwrap();
+        let p = palette.colors[entry];
+        quantized[index] = Rgba([p[0], p[1], p[2], c[3]]);
+
+        let neighbors: Vec<usize> = faces[index]
+            .adjacent_edges()
+            .filter_map(|edge| positions.get(&edge.sym().face().fix()).copied())
+            .filter(|neighbor| !visited[*neighbor])
+            .collect();
+        for neighbor in neighbors.iter() {
+            for i in 0..3 {
+                errors[*neighbor][i] += (desired[i] - entries[entry][i]) / neighbors.len() as f32;
+            }
+        }
+    }
+    quantized
+}
+
+fn working_values(c: Rgba<f32>, metric: ColorSpace) -> [f32; 3] {
+    let c = metric.decode(c);
+    [c[0], c[1], c[2]]
+}
+
+fn squared_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+// None if there are no entries
+fn nearest_index(entries: &[[f32; 3]], value: [f32; 3]) -> Option<usize> {
+    if entries.is_empty() {
+        return None;
+    }
+
+    let mut nearest = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        if squared_distance(entry, &value) < squared_distance(&entries[nearest], &value) {
+            nearest = i;
+        }
+    }
+    Some(nearest)
+}
+
+fn mean(values: &[[f32; 3]]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    for value in values.iter() {
+        for i in 0..3 {
+            sum[i] += value[i];
+        }
+    }
+    sum.map(|s| s / values.len() as f32)
+}
+
+// Splits the box with the widest range of values at the median of its widest
+// channel until there are `n_colors` boxes or no box can be split any more
+fn median_cut(values: &[[f32; 3]], n_colors: usize) -> Vec<[f32; 3]> {
+    if values.is_empty() {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![values.to_vec()];
+    while boxes.len() < n_colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, values)| values.len() > 1)
+            .map(|(i, values)| {
+                let ranges: Vec<f32> = (0..3)
+                    .map(|channel| {
+                        let min = values.iter().map(|v| v[channel]).fold(f32::INFINITY, f32::min);
+                        let max = values.iter().map(|v| v[channel]).fold(f32::NEG_INFINITY, f32::max);
+                        max - min
+                    })
+                    .collect();
+                let channel = (0..3).fold(0, |widest, c| if ranges[c] > ranges[widest] { c } else { widest });
+                (i, channel, ranges[channel])
+            })
+            .fold(None, |widest: Option<(usize, usize, f32)>, candidate| match widest {
+                Some(widest) if widest.2 >= candidate.2 => Some(widest),
+                _ => Some(candidate),
+            });
+
+        let (i, channel, range) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+        if range <= 0.0 {
+            break;
+        }
+
+        let mut values = boxes.swap_remove(i);
+        values.sort_by(|a, b| a[channel].partial_cmp(&b[channel]).unwrap());
+        let upper = values.split_off(values.len() / 2);
+        boxes.push(values);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|values| mean(values)).collect()
+}
+
+// Lloyd's algorithm, starting from the given centers. Centers without any
+// values assigned keep their position.
+fn k_means(values: &[[f32; 3]], centers: &mut [[f32; 3]]) {
+    let mut assignment = vec![usize::MAX; values.len()];
+    for _ in 0..MAX_KMEANS_ITERATIONS {
+        let mut changed = false;
+        for (value, assigned) in values.iter().zip(assignment.iter_mut()) {
+            let nearest = nearest_index(centers, *value).unwrap();
+            if nearest != *assigned {
+                *assigned = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        for (i, center) in centers.iter_mut().enumerate() {
+            let members: Vec<[f32; 3]> = values
+                .iter()
+                .zip(assignment.iter())
+                .filter(|(_, assigned)| **assigned == i)
+                .map(|(value, _)| *value)
+                .collect();
+            if !members.is_empty() {
+                *center = mean(&members);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use crate::test_util::create_square_triangulation;
+    use super::*;
+
+    const BLACK: Rgba<f32> = Rgba([0.0, 0.0, 0.0, 1.0]);
+    const WHITE: Rgba<f32> = Rgba([1.0, 1.0, 1.0, 1.0]);
+
+    #[test]
+    fn test_quantization_method_from_str() {
+        assert_eq!("median-cut".parse::<QuantizationMethod>(), Ok(QuantizationMethod::MedianCut));
+        assert_eq!("kmeans".parse::<QuantizationMethod>(), Ok(QuantizationMethod::KMeans));
+        assert!("octree".parse::<QuantizationMethod>().is_err());
+    }
+
+    #[test]
+    fn test_parse_palette_files() {
+        let gpl = "GIMP Palette\nName: Test\nColumns: 2\n# comment\n255 0 0 Red\n  0 0 255\tBlue\n";
+        assert_eq!(
+            parse_gpl(gpl),
+            Ok(Palette { colors: vec![Rgba([1.0, 0.0, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 1.0])] })
+        );
+        assert!(parse_gpl("GIMP Palette\n255 0\n").is_err());
+
+        assert_eq!(parse_hex_list("#000000 ffffff\n"), Ok(Palette { colors: vec![BLACK, WHITE] }));
+        assert!(parse_hex_list("#000000 white").is_err());
+    }
+
+    #[test]
+    fn test_generate_palette() {
+        let colors = vec![
+            Rgba([0.9, 0.1, 0.1, 1.0]),
+            Rgba([1.0, 0.0, 0.0, 1.0]),
+            Rgba([0.0, 0.0, 0.9, 1.0]),
+            Rgba([0.0, 0.1, 1.0, 1.0]),
+        ];
+
+        for method in [QuantizationMethod::MedianCut, QuantizationMethod::KMeans].iter() {
+            let palette = Palette::generate(&colors, 2, *method, ColorSpace::Srgb).unwrap();
+            let mut reds: Vec<f32> = palette.colors.iter().map(|c| c[0]).collect();
+            reds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            assert_eq!(palette.colors.len(), 2);
+            assert_eq!(reds, vec![0.0, 0.95]);
+        }
+
+        // There are never more colors than different input colors
+        let palette = Palette::generate(&[BLACK, BLACK], 4, QuantizationMethod::KMeans, ColorSpace::Srgb).unwrap();
+        assert_eq!(palette.colors.len(), 1);
+
+        // A fully transparent image has no colors to generate a palette from
+        assert!(Palette::generate(&[], 4, QuantizationMethod::MedianCut, ColorSpace::Srgb).is_err());
+    }
+
+    #[test]
+    fn test_nearest() {
+        let palette = Palette { colors: vec![BLACK, WHITE] };
+
+        assert_eq!(palette.nearest(Rgba([0.4, 0.4, 0.4, 0.5]), ColorSpace::Srgb), Rgba([0.0, 0.0, 0.0, 0.5]));
+        // Under the lightness of CIELAB, 48% gray is closer to white
+        assert_eq!(palette.nearest(Rgba([0.48, 0.48, 0.48, 1.0]), ColorSpace::Srgb), BLACK);
+        assert_eq!(palette.nearest(Rgba([0.48, 0.48, 0.48, 1.0]), ColorSpace::Lab), WHITE);
+
+        let empty = Palette { colors: Vec::new() };
+        assert_eq!(empty.nearest(Rgba([0.4, 0.4, 0.4, 0.5]), ColorSpace::Srgb), Rgba([0.4, 0.4, 0.4, 0.5]));
+    }
+
+    #[test]
+    fn test_quantize_faces() {
+        let mesh = create_square_triangulation(10.0, [Rgba([0.4, 0.4, 0.4, 1.0]); 4]);
+        let palette = Palette { colors: vec![BLACK, WHITE] };
+
+        let quantized = quantize_faces(&mesh, &palette, ColorSpace::Srgb, ColorSpace::Srgb, false);
+        assert_eq!(quantized, vec![BLACK, BLACK]);
+
+        // The error of the first triangle pushes its neighbor to white
+        let quantized = quantize_faces(&mesh, &palette, ColorSpace::Srgb, ColorSpace::Srgb, true);
+        let mut quantized: Vec<f32> = quantized.iter().map(|c| c[0]).collect();
+        quantized.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(quantized, vec![0.0, 1.0]);
+
+        let empty = Palette { colors: Vec::new() };
+        let colors = quantize_faces(&mesh, &empty, ColorSpace::Srgb, ColorSpace::Srgb, false);
+        assert_eq!(quantize_faces(&mesh, &empty, ColorSpace::Srgb, ColorSpace::Srgb, true), colors);
+    }
+}
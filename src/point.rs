@@ -2,15 +2,17 @@
 use image::Rgba;
 use spade::{PointN, TwoDimensional};
 
+// A vertex of the mesh. The color is stored with floating point channels
+// in [0, 1], so that images of any bit depth keep their precision.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
-    pub c: Rgba<u8>,
+    pub c: Rgba<f32>,
 }
 
 impl Point {
-    pub fn new(x: f32, y: f32, c: Rgba<u8>) -> Point {
+    pub fn new(x: f32, y: f32, c: Rgba<f32>) -> Point {
         Point { x, y, c }
     }
 }
@@ -28,7 +30,7 @@ impl PointN for Point {
         Point {
             x: value,
             y: value,
-            c: Rgba([255, 255, 255, 255]),
+            c: Rgba([1.0, 1.0, 1.0, 1.0]),
         }
     }
 
@@ -58,7 +60,7 @@ mod point_tests {
     fn test_construction() {
         let x = 10.0;
         let y = 20.0;
-        let c = Rgba([1, 2, 3, 4]);
+        let c = Rgba([0.1, 0.2, 0.3, 0.4]);
 
         let p = Point::new(x, y, c);
         
@@ -74,14 +76,14 @@ mod point_tests {
 
         assert_eq!(p.x, value);
         assert_eq!(p.y, value);
-        assert_eq!(p.c, Rgba([255; 4]));
+        assert_eq!(p.c, Rgba([1.0; 4]));
     }
 
     #[test]
     fn test_nth() {
         let x = 10.0;
         let y = 20.0;
-        let c = Rgba([1, 2, 3, 4]);
+        let c = Rgba([0.1, 0.2, 0.3, 0.4]);
 
         let mut p = Point::new(x, y, c);
         
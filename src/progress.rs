@@ -0,0 +1,139 @@
+// Standard library imports
+use std::cell::Cell;
+use std::time::Duration;
+
+// Community library imports
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// Crate imports
+use crate::report::IterationStatistics;
+
+// Stage of the pipeline whose progress is reported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Stage {
+    // Placing the initial nodes, counted in nodes.
+    Seeding,
+    // An iteration of the refinement at a level of the pyramid, 0 being
+    // full resolution, counted in triangles.
+    Refinement { level: usize, iteration: usize },
+    // Rasterizing the mesh, counted in triangles.
+    Rasterization,
+}
+
+// Receives the progress of the pipeline, so that embedders can show it in
+// their own way.
+pub trait Observer {
+    // A stage starts that processes the given number of items.
+    fn stage_started(&self, _stage: Stage, _total: usize) {}
+
+    // The given number of items of the current stage were processed.
+    fn advanced(&self, _n: usize) {}
+
+    fn stage_finished(&self) {}
+
+    // An iteration of the refinement inserted its points into the mesh.
+    fn iteration_finished(&self, _statistics: &IterationStatistics) {}
+}
+
+// Ignores all progress.
+pub struct NoProgress;
+
+impl Observer for NoProgress {}
+
+// Shows the progress of a single image as a bar on the terminal, below the
+// bars of the other images processed at the same time.
+pub struct ProgressBars {
+    bars: MultiProgress,
+    bar: ProgressBar,
+    last_iteration: Cell<Option<IterationStatistics>>,
+}
+
+impl ProgressBars {
+    pub fn new(bars: &MultiProgress, name: &str) -> ProgressBars {
+        let style = ProgressStyle::with_template("{prefix:.bold} {msg} [{bar:30}] {pos}/{len}")
+            .unwrap()
+            .progress_chars("=> ");
+        let bar = bars.add(ProgressBar::new(0).with_style(style).with_prefix(name.to_string()));
+        bar.enable_steady_tick(Duration::from_millis(200));
+        ProgressBars { bars: bars.clone(), bar, last_iteration: Cell::new(None) }
+    }
+}
+
+impl Observer for ProgressBars {
+    fn stage_started(&self, stage: Stage, total: usize) {
+        self.bar.reset();
+        self.bar.set_length(total as u64);
+        self.bar.set_message(match (stage, self.last_iteration.get()) {
+            (Stage::Seeding, _) => "seeding".to_string(),
+            (Stage::Refinement { level, iteration }, Some(last)) => format!(
+                "refining level {}, iteration {} ({} points inserted before, {} vertices)",
+                level, iteration, last.inserted, last.n_vertices
+            ),
+            (Stage::Refinement { level, iteration }, None) => format!("refining level {}, iteration {}", level, iteration),
+            (Stage::Rasterization, _) => "rasterizing".to_string(),
+        });
+    }
+
+    fn advanced(&self, n: usize) {
+        self.bar.inc(n as u64);
+    }
+
+    fn iteration_finished(&self, statistics: &IterationStatistics) {
+        self.last_iteration.set(Some(*statistics));
+        log::debug!(
+            "{}: inserted {} points at level {}, {} vertices",
+            self.bar.prefix(),
+            statistics.inserted,
+            statistics.level,
+            statistics.n_vertices
+        );
+    }
+}
+
+impl Drop for ProgressBars {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        self.bars.remove(&self.bar);
+    }
+}
+
+// Prints the messages of the `log` facade as they are, warnings and errors
+// to stderr, without tearing the progress bars.
+struct TerminalLogger {
+    level: LevelFilter,
+    bars: MultiProgress,
+}
+
+impl Log for TerminalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.bars.suspend(|| match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}: {}", record.level(), record.args()),
+            _ => println!("{}", record.args()),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+// Installs the logger for the given verbosity and returns the progress bars
+// to add the bars of the images to.
+pub fn init_terminal(level: LevelFilter) -> MultiProgress {
+    let bars = if level >= LevelFilter::Info {
+        MultiProgress::new()
+    } else {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    };
+    let logger = TerminalLogger { level, bars: bars.clone() };
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+    bars
+}
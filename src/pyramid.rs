@@ -0,0 +1,200 @@
+// Community library imports
+use image::{Rgba, Rgba32FImage};
+use spade::delaunay::DelaunayLocateStructure;
+
+// Crate imports
+use crate::point::Point;
+use crate::Mesh;
+
+// Levels are not made smaller than this in either dimension
+const MIN_LEVEL_SIZE: u32 = 16;
+
+// Binomial approximation of a Gaussian kernel
+const KERNEL: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+
+// Builds a Gaussian pyramid of at most `n_levels` levels, the first being the
+// image itself and every further one blurred and halved in size.
+pub fn gaussian_pyramid(img: &Rgba32FImage, n_levels: usize) -> Vec<Rgba32FImage> {
+    let mut levels = vec![img.clone()];
+    while levels.len() < n_levels {
+        let (width, height) = levels.last().unwrap().dimensions();
+        if width / 2 < MIN_LEVEL_SIZE || height / 2 < MIN_LEVEL_SIZE {
+            break;
+        }
+        let next = downsample(levels.last().unwrap());
+        levels.push(next);
+    }
+    levels
+}
+
+// Splits the iterations between the levels, indexed like the pyramid.
+// Coarser levels get the remainder, so that fewer passes are run at full
+// resolution, but the finest level always gets at least one pass.
+pub fn split_iterations(n_iterations: usize, n_levels: usize) -> Vec<usize> {
+    let mut split: Vec<usize> = (0..n_levels)
+        .map(|level| n_iterations / n_levels + usize::from(n_levels - 1 - level < n_iterations % n_levels))
+        .collect();
+    if n_iterations > 0 && split[0] == 0 {
+        split[0] = 1;
+        split[n_levels - 1] -= 1;
+    }
+    split
+}
+
+// Blurs the image and keeps every second pixel. The colors are premultiplied
+// by alpha while blurring, so that transparent pixels do not bleed in.
+fn downsample(img: &Rgba32FImage) -> Rgba32FImage {
+    let (width, height) = img.dimensions();
+    let premultiplied = |c: &Rgba<f32>| [c[0] * c[3], c[1] * c[3], c[2] * c[3], c[3]];
+
+    // Horizontal pass at every second column
+    let half_width = width.div_ceil(2);
+    let mut horizontal = vec![[0.0f32; 4]; (half_width * height) as usize];
+    for row in 0..height {
+        for col in 0..half_width {
+            let mut sum = [0.0; 4];
+            for (k, weight) in KERNEL.iter().enumerate() {
+                let x = (2 * col as i64 + k as i64 - 2).clamp(0, width as i64 - 1) as u32;
+                let c = premultiplied(img.get_pixel(x, row));
+                for i in 0..4 {
+                    sum[i] += weight * c[i];
+                }
+            }
+            horizontal[(row * half_width + col) as usize] = sum;
+        }
+    }
+
+    // Vertical pass at every second row
+    let half_height = height.div_ceil(2);
+    Rgba32FImage::from_fn(half_width, half_height, |col, row| {
+        let mut sum = [0.0; 4];
+        for (k, weight) in KERNEL.iter().enumerate() {
+            let y = (2 * row as i64 + k as i64 - 2).clamp(0, height as i64 - 1) as u32;
+            let c = horizontal[(y * half_width + col) as usize];
+            for i in 0..4 {
+                sum[i] += weight * c[i];
+            }
+        }
+        if sum[3] > 0.0 {
+            Rgba([sum[0] / sum[3], sum[1] / sum[3], sum[2] / sum[3], sum[3]])
+        } else {
+            Rgba([0.0; 4])
+        }
+    })
+}
+
+// Scales the mesh, which was created for an image of the given size, to the
+// size of the image, keeping the corners in the corners.
+pub fn upscale_mesh<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: u32,
+    height: u32,
+    img: &Rgba32FImage,
+) -> Mesh<L> {
+    let (new_width, new_height) = img.dimensions();
+    scale_vertices(mesh, (width, height), (new_width, new_height), |x, y, _| *img.get_pixel(x as u32, y as u32))
+}
+
+// Scales the mesh, which was created for an image of the given size, to an
+// image of the new size, keeping the vertex colors.
+pub fn scale_mesh<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: u32,
+    height: u32,
+    new_width: u32,
+    new_height: u32,
+) -> Mesh<L> {
+    scale_vertices(mesh, (width, height), (new_width, new_height), |_, _, c| c)
+}
+
+fn scale_vertices<F, L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    size: (u32, u32),
+    new_size: (u32, u32),
+    color: F,
+) -> Mesh<L>
+where
+    F: Fn(f32, f32, Rgba<f32>) -> Rgba<f32>,
+{
+    let scale_x = (new_size.0 - 1) as f32 / (size.0 - 1) as f32;
+    let scale_y = (new_size.1 - 1) as f32 / (size.1 - 1) as f32;
+
+    let mut delaunay = Mesh::new();
+    for vertex in mesh.vertices() {
+        let x = (vertex.x * scale_x).min((new_size.0 - 1) as f32);
+        let y = (vertex.y * scale_y).min((new_size.1 - 1) as f32);
+        delaunay.insert(Point::new(x, y, color(x, y, vertex.c)));
+    }
+    delaunay
+}
+
+#[cfg(test)]
+mod pyramid_tests {
+    use spade::delaunay::FloatDelaunayTriangulation;
+    use super::*;
+
+    #[test]
+    fn test_gaussian_pyramid() {
+        let img = Rgba32FImage::from_fn(100, 40, |x, _| {
+            if x < 50 {
+                Rgba([1.0, 0.0, 0.0, 1.0])
+            } else {
+                Rgba([0.0, 0.0, 1.0, 0.0])
+            }
+        });
+        let pyramid = gaussian_pyramid(&img, 4);
+
+        // The third level would be smaller than 16 pixels
+        assert_eq!(pyramid.len(), 2);
+        assert_eq!(pyramid[1].dimensions(), (50, 20));
+        assert_eq!(*pyramid[1].get_pixel(10, 10), Rgba([1.0, 0.0, 0.0, 1.0]));
+
+        // At the border, the color of the transparent half does not bleed in
+        let border = pyramid[1].get_pixel(25, 10);
+        assert_eq!(border[0], 1.0);
+        assert!(border[3] > 0.0 && border[3] < 1.0);
+    }
+
+    #[test]
+    fn test_split_iterations() {
+        assert_eq!(split_iterations(7, 3), vec![2, 2, 3]);
+        assert_eq!(split_iterations(2, 1), vec![2]);
+        assert_eq!(split_iterations(0, 3), vec![0, 0, 0]);
+        // The finest level gets the pass of the coarsest one
+        assert_eq!(split_iterations(1, 3), vec![1, 0, 0]);
+        assert_eq!(split_iterations(2, 3), vec![1, 1, 0]);
+    }
+
+    #[test]
+    fn test_upscale_mesh() {
+        let mut mesh = FloatDelaunayTriangulation::with_walk_locate();
+        mesh.insert(Point::new(0.0, 0.0, Rgba([0.0; 4])));
+        mesh.insert(Point::new(9.0, 0.0, Rgba([0.0; 4])));
+        mesh.insert(Point::new(0.0, 4.0, Rgba([0.0; 4])));
+        mesh.insert(Point::new(9.0, 4.0, Rgba([0.0; 4])));
+        mesh.insert(Point::new(9.5, 2.0, Rgba([0.0; 4])));
+
+        let img = Rgba32FImage::from_pixel(19, 9, Rgba([0.5, 0.5, 0.5, 1.0]));
+        let upscaled = upscale_mesh(&mesh, 10, 5, &img);
+        let mut positions: Vec<(f32, f32)> = upscaled.vertices().map(|vertex| (vertex.x, vertex.y)).collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        assert_eq!(positions, vec![(0.0, 0.0), (0.0, 8.0), (18.0, 0.0), (18.0, 4.0), (18.0, 8.0)]);
+        assert!(upscaled.vertices().all(|vertex| vertex.c == Rgba([0.5, 0.5, 0.5, 1.0])));
+    }
+
+    #[test]
+    fn test_scale_mesh() {
+        let mut mesh = FloatDelaunayTriangulation::with_walk_locate();
+        mesh.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+        mesh.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 1.0])));
+        mesh.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 1.0])));
+        mesh.insert(Point::new(10.0, 10.0, Rgba([1.0, 1.0, 1.0, 1.0])));
+
+        // Downscaling works as well and the colors are kept
+        let scaled = scale_mesh(&mesh, 11, 11, 6, 21);
+        let vertex = scaled.vertices().find(|vertex| vertex.c == Rgba([1.0, 1.0, 1.0, 1.0])).unwrap();
+        assert_eq!((vertex.x, vertex.y), (5.0, 20.0));
+        assert_eq!(scaled.num_triangles(), 2);
+    }
+}
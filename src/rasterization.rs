@@ -1,35 +1,212 @@
+// Standard library imports
+use std::str::FromStr;
+
 // Community library imports
-use image::{ImageBuffer, Rgba, RgbaImage};
-use spade::delaunay::{DelaunayTriangulation, DelaunayWalkLocate};
-use spade::kernels::FloatKernel;
+use image::{Rgba, Rgba32FImage};
+use serde::{Deserialize, Serialize};
+use spade::delaunay::{DelaunayLocateStructure, VertexHandle};
 
 // Crate imports
 use crate::boundingbox::BoundingBox;
-use crate::interpolation::interpolate_triangle_average_color;
+use crate::cancellation::CancellationToken;
+use crate::color::ColorSpace;
+use crate::interpolation::{interpolate_rgba_in_triangle, interpolate_triangle_average_color};
 use crate::point::Point;
-use crate::topology::is_point_in_triangle;
+use crate::progress::Observer;
+use crate::Mesh;
 
-// Type definitions
-type Mesh = DelaunayTriangulation<Point, FloatKernel, DelaunayWalkLocate>;
 // type Element = [VertexHandle<Point>; 3];
 
-pub fn rasterize_mesh(mesh: &Mesh, width: i32, height: i32) -> RgbaImage {
-    let mut img = ImageBuffer::new(width as u32, height as u32);
-    for face in mesh.triangles() {
+// How the pixels inside a triangle are colored.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Shading {
+    // Every triangle gets the average color of its vertices.
+    Flat,
+    // The vertex colors are interpolated across the triangle.
+    Gouraud,
+}
+
+impl FromStr for Shading {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shading, String> {
+        match s {
+            "flat" => Ok(Shading::Flat),
+            "gouraud" => Ok(Shading::Gouraud),
+            _ => Err(format!("unknown shading mode '{}'", s)),
+        }
+    }
+}
+
+// Rasterizes the mesh with the given shading, or fills the triangles with
+// the face colors in the order of `mesh.triangles()` if given. Pixels on the
+// edges mix the premultiplied colors of the triangles by their coverage.
+#[allow(clippy::too_many_arguments)]
+pub fn rasterize_mesh<L: DelaunayLocateStructure<Point>>(
+    mesh: &Mesh<L>,
+    width: i32,
+    height: i32,
+    shading: Shading,
+    space: ColorSpace,
+    face_colors: Option<&[Rgba<f32>]>,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> Rgba32FImage {
+    let (width, height) = (width as u32, height as u32);
+    // Premultiplied color, alpha and coverage summed over the triangles
+    let mut sums = vec![[0.0f32; 5]; (width * height) as usize];
+    for (i, face) in mesh.triangles().enumerate() {
+        if token.is_cancelled() {
+            break;
+        }
+        observer.advanced(1);
         let triangle = face.as_triangle();
+
         let bbox = BoundingBox::from_triangle(&triangle);
+        let transparent = triangle.iter().all(|vertex| vertex.c[3] == 0.0);
+        let (shading, average_color) = match face_colors {
+            _ if transparent => (Shading::Flat, Rgba([0.0; 4])),
+            Some(colors) => (Shading::Flat, colors[i]),
+            None => (shading, interpolate_triangle_average_color(&triangle, space)),
+        };
 
-        for row in (bbox.ymin.floor() as u32)..(bbox.ymax.ceil() as u32) {
-            for col in (bbox.xmin.floor() as u32)..(bbox.xmax.ceil() as u32) {
-                let point = Point::new(col as f32, row as f32, Rgba([255, 255, 255, 255]));
+        // Pixels closer than half a pixel to the triangle are partly covered
+        let rows = (bbox.ymin - 0.5).ceil().max(0.0) as u32..=(bbox.ymax + 0.5).floor().min(height as f32 - 1.0) as u32;
+        let cols = (bbox.xmin - 0.5).ceil().max(0.0) as u32..=(bbox.xmax + 0.5).floor().min(width as f32 - 1.0) as u32;
+        for row in rows {
+            for col in cols.clone() {
+                let point = Point::new(col as f32, row as f32, Rgba([1.0, 1.0, 1.0, 1.0]));
+                let covered = coverage(&point, &triangle);
+                if covered <= 0.0 {
+                    continue;
+                }
 
-                if is_point_in_triangle(&point, &triangle) {
-                    // let color = interpolate_rgba_in_triangle(&point, &triangle);
-                    let color = interpolate_triangle_average_color(&triangle);
-                    img.put_pixel(col, row, color);
+                let color = match shading {
+                    Shading::Flat => average_color,
+                    Shading::Gouraud => {
+                        // Outside of the triangle the colors are extrapolated
+                        let color = interpolate_rgba_in_triangle(&point, &triangle, space);
+                        Rgba(color.0.map(|channel| channel.clamp(0.0, 1.0)))
+                    }
+                };
+                let sum = &mut sums[(row * width + col) as usize];
+                for c in 0..3 {
+                    sum[c] += covered * color[3] * color[c];
                 }
+                sum[3] += covered * color[3];
+                sum[4] += covered;
             }
         }
     }
-    RgbaImage::from(img)
+
+    // Pixels on the border of the mesh are only partly covered, but not
+    // transparent
+    Rgba32FImage::from_fn(width, height, |col, row| {
+        let [red, green, blue, alpha, covered] = sums[(row * width + col) as usize];
+        if alpha <= 0.0 {
+            Rgba([0.0; 4])
+        } else {
+            Rgba([red / alpha, green / alpha, blue / alpha, alpha / covered])
+        }
+    })
+}
+
+// Fraction of the pixel at the point that the triangle covers, estimated from
+// the signed distance of the point to the closest edge
+fn coverage(point: &Point, triangle: &[VertexHandle<Point>; 3]) -> f32 {
+    let (a, b, c) = (&triangle[0], &triangle[1], &triangle[2]);
+    let orientation = ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)).signum();
+    let mut distance = f32::MAX;
+    for i in 0..3 {
+        let (from, to) = (&triangle[i], &triangle[(i + 1) % 3]);
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = (dx * dx + dy * dy).sqrt();
+        distance = distance.min(orientation * (dx * (point.y - from.y) - dy * (point.x - from.x)) / length);
+    }
+    (0.5 + distance).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod rasterization_tests {
+    use crate::test_util::create_simple_triangulation;
+    use crate::progress::NoProgress;
+    use super::*;
+
+    fn rasterize(mesh: &Mesh, shading: Shading, face_colors: Option<&[Rgba<f32>]>) -> Rgba32FImage {
+        rasterize_mesh(mesh, 10, 10, shading, ColorSpace::Srgb, face_colors, &NoProgress, &CancellationToken::new())
+    }
+
+    #[test]
+    fn test_shading_from_str() {
+        assert_eq!("flat".parse::<Shading>(), Ok(Shading::Flat));
+        assert_eq!("gouraud".parse::<Shading>(), Ok(Shading::Gouraud));
+        assert!("phong".parse::<Shading>().is_err());
+    }
+
+    #[test]
+    fn test_rasterize_flat() {
+        let mesh = create_simple_triangulation();
+        let img = rasterize(&mesh, Shading::Flat, None);
+
+        assert_eq!(*img.get_pixel(1, 1), Rgba([1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0, 1.0]));
+        assert_eq!(*img.get_pixel(5, 1), Rgba([1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0, 1.0]));
+        assert_eq!(*img.get_pixel(9, 9), Rgba([0.0; 4]));
+    }
+
+    #[test]
+    fn test_rasterize_gouraud() {
+        let mesh = create_simple_triangulation();
+        let img = rasterize(&mesh, Shading::Gouraud, None);
+
+        assert_eq!(*img.get_pixel(0, 0), Rgba([1.0, 0.0, 0.0, 1.0]));
+        assert_ne!(*img.get_pixel(1, 1), *img.get_pixel(5, 1));
+    }
+
+    #[test]
+    fn test_rasterize_face_colors() {
+        let mesh = create_simple_triangulation();
+        let colors = [Rgba([0.5, 0.5, 0.5, 1.0])];
+        let img = rasterize(&mesh, Shading::Gouraud, Some(&colors));
+
+        assert_eq!(*img.get_pixel(0, 0), colors[0]);
+        assert_eq!(*img.get_pixel(5, 1), colors[0]);
+        assert_eq!(*img.get_pixel(9, 9), Rgba([0.0; 4]));
+    }
+
+    #[test]
+    fn test_rasterize_blends_edges() {
+        let mut mesh = create_simple_triangulation();
+        mesh.insert(Point::new(12.0, 12.0, Rgba([1.0; 4])));
+
+        // Pixels on the shared edge mix both triangles
+        let colors = [Rgba([1.0, 0.0, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 1.0])];
+        let img = rasterize(&mesh, Shading::Flat, Some(&colors));
+        assert_eq!(*img.get_pixel(5, 5), Rgba([0.5, 0.0, 0.5, 1.0]));
+
+        // Next to a transparent triangle, the edge is half transparent
+        // without the color of the transparent triangle bleeding in
+        let colors = [Rgba([1.0, 0.0, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 0.0])];
+        let img = rasterize(&mesh, Shading::Flat, Some(&colors));
+        assert_eq!(*img.get_pixel(5, 5), Rgba([1.0, 0.0, 0.0, 0.5]));
+    }
+
+    // Counts the items reported to it
+    struct Counter(std::cell::Cell<usize>);
+
+    impl Observer for Counter {
+        fn advanced(&self, n: usize) {
+            self.0.set(self.0.get() + n);
+        }
+    }
+
+    #[test]
+    fn test_rasterize_reports_progress() {
+        let mut mesh = create_simple_triangulation();
+        mesh.insert(Point::new(10.0, 10.0, Rgba([1.0; 4])));
+        let counter = Counter(std::cell::Cell::new(0));
+        rasterize_mesh(&mesh, 10, 10, Shading::Flat, ColorSpace::Srgb, None, &counter, &CancellationToken::new());
+
+        assert_eq!(counter.0.get(), 2);
+    }
 }
@@ -1,36 +1,276 @@
 // Community library imports
-use spade::kernels::FloatKernel;
-use spade::delaunay::{DelaunayTriangulation, DelaunayWalkLocate, VertexHandle};
-use image::{DynamicImage, GenericImageView};
+use spade::delaunay::{DelaunayLocateStructure, FixedFaceHandle, FixedVertexHandle};
+use image::Rgba32FImage;
 
 // Crate imports
+use crate::cancellation::CancellationToken;
+use crate::color::{premultiplied_difference, ColorSpace};
+use crate::importance::ImportanceMap;
+use crate::palette::Palette;
 use crate::point::Point;
+use crate::progress::Observer;
+use crate::tolerance::ToleranceMap;
 use crate::interpolation::interpolate_triangle_centroid;
+use crate::locate::{insert_points, InsertionOrder};
+use crate::Mesh;
 
-// Type definitions
-type Mesh = DelaunayTriangulation<Point, FloatKernel, DelaunayWalkLocate>;
+// Decides which triangles are refined.
+#[derive(Debug, Copy, Clone)]
+pub struct RefinementCriteria<'a> {
+    // Largest tolerated difference in any channel, given in 8 bit steps
+    // regardless of the bit depth of the image.
+    pub max_diff: i32,
+    // Leaves triangles between fully transparent vertices as they are.
+    pub skip_transparent: bool,
+    // The color space in which colors are compared.
+    pub space: ColorSpace,
+    // If given, the palette colors of the triangle and of the image are
+    // compared instead, so that only triangles whose quantized color is wrong
+    // are refined.
+    pub palette: Option<&'a Palette>,
+    // The color space in which the nearest palette color is searched.
+    pub palette_metric: ColorSpace,
+    // If given, `max_diff` is raised outside the regions of interest, up
+    // to `importance_strength` times for unimportant pixels.
+    pub importance: Option<&'a ImportanceMap>,
+    pub importance_strength: f32,
+    // If given, `max_diff` is scaled by the local contrast of the image.
+    pub tolerance: Option<&'a ToleranceMap>,
+}
+
+// The triangles that changed since the last refinement.
+#[derive(Debug, Clone, Default)]
+pub struct ChangedFaces {
+    // `None` if every triangle has to be checked.
+    faces: Option<Vec<FixedFaceHandle>>,
+}
 
+impl ChangedFaces {
+    // Every triangle of the mesh, to start with and whenever the mesh or
+    // the criteria were replaced.
+    pub fn all() -> ChangedFaces {
+        ChangedFaces::default()
+    }
 
-pub fn refine_mesh_by_centroid(mesh: &mut Mesh, img: &DynamicImage, max_diff: i32) {
+    // The triangles around the given vertices, ordered like the triangles of
+    // the mesh.
+    fn around<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>, vertices: &[FixedVertexHandle]) -> ChangedFaces {
+        let mut faces: Vec<FixedFaceHandle> = vertices
+            .iter()
+            .flat_map(|&vertex| mesh.vertex(vertex).ccw_out_edges().map(|edge| edge.face().fix()))
+            .filter(|&face| face != mesh.infinite_face().fix())
+            .collect();
+        faces.sort_unstable();
+        faces.dedup();
+        ChangedFaces { faces: Some(faces) }
+    }
+
+    // The number of triangles to check in the given mesh.
+    pub fn num_triangles<L: DelaunayLocateStructure<Point>>(&self, mesh: &Mesh<L>) -> usize {
+        self.faces.as_ref().map_or(mesh.num_triangles(), |faces| faces.len())
+    }
+}
+
+// Inserts the centroids of the changed triangles whose color differs from the
+// image by more than the criteria tolerate and returns their number
+pub fn refine_mesh_by_centroid<L: DelaunayLocateStructure<Point>>(
+    mesh: &mut Mesh<L>,
+    img: &Rgba32FImage,
+    criteria: &RefinementCriteria,
+    changed: &mut ChangedFaces,
+    order: InsertionOrder,
+    observer: &dyn Observer,
+    token: &CancellationToken,
+) -> usize {
+    let max_diff = criteria.max_diff as f32 / 255.0;
+    let space = criteria.space;
     let mut new_points = Vec::new();
 
-    for face in mesh.triangles() {
-        let triangle = face.as_triangle();
-        let mut centroid = interpolate_triangle_centroid(&triangle);
-        let orig_color = img.get_pixel(centroid.x as u32, centroid.y as u32);
+    let faces = match changed.faces.take() {
+        Some(faces) => faces,
+        None => mesh.triangles().map(|face| face.fix()).collect(),
+    };
+    let mut stopped = false;
+    for face in faces {
+        if token.should_stop() {
+            stopped = true;
+            break;
+        }
+        observer.advanced(1);
+        let triangle = mesh.face(face).as_triangle();
+        if criteria.skip_transparent && triangle.iter().all(|vertex| vertex.c[3] == 0.0) {
+            continue;
+        }
 
-        let diff_r = ((centroid.c[0] as i32) - (orig_color[0] as i32)).abs();
-        let diff_g = ((centroid.c[1] as i32) - (orig_color[1] as i32)).abs();
-        let diff_b = ((centroid.c[2] as i32) - (orig_color[2] as i32)).abs();
+        let mut centroid = interpolate_triangle_centroid(&triangle, space);
+        let orig_color = *img.get_pixel(centroid.x as u32, centroid.y as u32);
+        let (interpolated, expected) = match criteria.palette {
+            Some(palette) => (
+                palette.nearest(centroid.c, criteria.palette_metric),
+                palette.nearest(orig_color, criteria.palette_metric),
+            ),
+            None => (centroid.c, orig_color),
+        };
 
-        if diff_r > max_diff || diff_g > max_diff || diff_b > max_diff {
+        let mut tolerance = max_diff;
+        if let Some(importance) = criteria.importance {
+            tolerance *= importance.threshold_scale(centroid.x, centroid.y, criteria.importance_strength);
+        }
+        if let Some(map) = criteria.tolerance {
+            tolerance *= map.get(centroid.x, centroid.y);
+        }
+
+        if premultiplied_difference(space.decode(interpolated), space.decode(expected)) > tolerance {
             centroid.c = orig_color;
             new_points.push(centroid);
         }
     }
 
-    for point in new_points {
-        mesh.insert(point);
+    let n_inserted = new_points.len();
+    let inserted = insert_points(mesh, new_points, order);
+    if !stopped {
+        *changed = ChangedFaces::around(mesh, &inserted);
     }
+    n_inserted
 }
 
+#[cfg(test)]
+mod refinement_tests {
+    use image::Rgba;
+    use crate::importance::Region;
+    use crate::progress::NoProgress;
+    use crate::test_util::create_square_triangulation;
+    use super::*;
+
+    fn refine(mesh: &mut Mesh, img: &Rgba32FImage, criteria: &RefinementCriteria) -> usize {
+        refine_changed(mesh, img, criteria, &mut ChangedFaces::all(), &CancellationToken::new())
+    }
+
+    fn refine_changed(
+        mesh: &mut Mesh,
+        img: &Rgba32FImage,
+        criteria: &RefinementCriteria,
+        changed: &mut ChangedFaces,
+        token: &CancellationToken,
+    ) -> usize {
+        refine_mesh_by_centroid(mesh, img, criteria, changed, InsertionOrder::Hilbert, &NoProgress, token)
+    }
+
+    fn create_criteria() -> RefinementCriteria<'static> {
+        RefinementCriteria {
+            max_diff: 15,
+            skip_transparent: false,
+            space: ColorSpace::Srgb,
+            palette: None,
+            palette_metric: ColorSpace::Srgb,
+            importance: None,
+            importance_strength: 4.0,
+            tolerance: None,
+        }
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.8, 0.0, 0.0, 1.0]));
+
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.8, 0.0, 0.0, 1.0]); 4]);
+        refine(&mut mesh, &img, &create_criteria());
+        assert_eq!(mesh.num_vertices(), 4);
+
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        assert_eq!(refine(&mut mesh, &img, &create_criteria()), 2);
+        assert_eq!(mesh.num_vertices(), 6);
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_alpha() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.8, 0.0, 0.0, 0.5]));
+
+        // Only the alpha differs
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.8, 0.0, 0.0, 1.0]); 4]);
+        refine(&mut mesh, &img, &create_criteria());
+        assert_eq!(mesh.num_vertices(), 6);
+
+        // Transparent vertices are skipped entirely
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0; 4]); 4]);
+        refine(&mut mesh, &img, &RefinementCriteria { skip_transparent: true, ..create_criteria() });
+        assert_eq!(mesh.num_vertices(), 4);
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_stopped() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.8, 0.0, 0.0, 1.0]));
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let mut changed = ChangedFaces::all();
+        assert_eq!(refine_changed(&mut mesh, &img, &create_criteria(), &mut changed, &token), 0);
+        assert_eq!(mesh.num_vertices(), 4);
+        assert_eq!(changed.num_triangles(&mesh), 2);
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_incremental() {
+        let img = Rgba32FImage::from_fn(64, 64, |x, y| Rgba([x as f32 / 63.0, y as f32 / 63.0, 0.0, 1.0]));
+        let token = CancellationToken::new();
+        let mut full = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        let mut incremental = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        let mut changed = ChangedFaces::all();
+
+        // Checking only the changed triangles inserts the same points
+        for _ in 0..5 {
+            assert!(changed.num_triangles(&incremental) <= incremental.num_triangles());
+            let inserted = refine(&mut full, &img, &create_criteria());
+            assert_eq!(refine_changed(&mut incremental, &img, &create_criteria(), &mut changed, &token), inserted);
+        }
+        assert!(changed.num_triangles(&incremental) < incremental.num_triangles());
+        let positions = |mesh: &Mesh| mesh.vertices().map(|vertex| (vertex.x, vertex.y)).collect::<Vec<_>>();
+        assert_eq!(positions(&incremental), positions(&full));
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_linear() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.1, 0.1, 0.1, 1.0]));
+
+        // Dark shades are far apart when sRGB encoded but close in linear light
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        refine(&mut mesh, &img, &create_criteria());
+        assert_eq!(mesh.num_vertices(), 6);
+
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        refine(&mut mesh, &img, &RefinementCriteria { space: ColorSpace::Linear, ..create_criteria() });
+        assert_eq!(mesh.num_vertices(), 4);
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_palette() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.3, 0.3, 0.3, 1.0]));
+        let palette = Palette { colors: vec![Rgba([0.0, 0.0, 0.0, 1.0]), Rgba([1.0, 1.0, 1.0, 1.0])] };
+        let criteria = RefinementCriteria { palette: Some(&palette), ..create_criteria() };
+
+        // Both colors are quantized to black
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+        refine(&mut mesh, &img, &criteria);
+        assert_eq!(mesh.num_vertices(), 4);
+
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.8, 0.8, 0.8, 1.0]); 4]);
+        refine(&mut mesh, &img, &criteria);
+        assert_eq!(mesh.num_vertices(), 6);
+    }
+
+    #[test]
+    fn test_refine_mesh_by_centroid_importance() {
+        let img = Rgba32FImage::from_pixel(10, 10, Rgba([0.1, 0.0, 0.0, 1.0]));
+        let mut mesh = create_square_triangulation(9.0, [Rgba([0.0, 0.0, 0.0, 1.0]); 4]);
+
+        // The difference of 0.1 is tolerated in the unimportant right half
+        let regions = [Region::Rectangle { x: 0.0, y: 0.0, width: 5.0, height: 10.0 }];
+        let importance = ImportanceMap::from_regions(&regions, 10, 10);
+        let criteria = RefinementCriteria { importance: Some(&importance), ..create_criteria() };
+        refine(&mut mesh, &img, &criteria);
+
+        assert_eq!(mesh.num_vertices(), 5);
+        assert!(mesh.vertices().any(|vertex| vertex.x == 3.0));
+    }
+}
@@ -0,0 +1,212 @@
+// Standard library imports
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// Community library imports
+use serde::Serialize;
+use spade::delaunay::DelaunayLocateStructure;
+
+// Crate imports
+use crate::color::ColorSpace;
+use crate::config::Config;
+use crate::filter::Filter;
+use crate::locate::{InsertionOrder, LocateStrategy};
+use crate::metrics::QualityMetrics;
+use crate::point::Point;
+use crate::rasterization::Shading;
+use crate::Mesh;
+
+// Triangle angles are counted in bins of this many degrees
+const ANGLE_BIN_WIDTH: f64 = 10.0;
+
+// Triangle areas are counted in bins doubling in size, the first one
+// holding all triangles smaller than this many pixels
+const SMALLEST_AREA_BIN: f64 = 0.25;
+
+// Everything worth tracking about a run of the command line program.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub parameters: ReportParameters,
+    pub seed: u64,
+    pub iterations: Vec<IterationStatistics>,
+    pub mesh: MeshStatistics,
+    pub timings: Vec<StageTiming>,
+    pub metrics: QualityMetrics,
+    // Whether the refinement was stopped by the time budget.
+    pub budget_expired: bool,
+}
+
+// The parameters the run was started with.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportParameters {
+    pub path_in: PathBuf,
+    pub path_out: PathBuf,
+    pub n_initial_points: usize,
+    pub n_iterations: usize,
+    pub max_diff: i32,
+    pub skip_transparent: bool,
+    pub prefilter: Option<Filter>,
+    pub sample_original: bool,
+    pub pyramid_levels: usize,
+    // In seconds.
+    pub time_budget: Option<f64>,
+    pub locate: LocateStrategy,
+    pub insertion_order: InsertionOrder,
+    pub adaptive_strength: Option<f32>,
+    pub adaptive_radius: u32,
+    pub roi_strength: f32,
+    pub shading: Shading,
+    pub color_space: ColorSpace,
+    pub palette: Option<String>,
+    pub palette_metric: ColorSpace,
+    pub path_mesh_in: Option<PathBuf>,
+}
+
+impl ReportParameters {
+    pub fn from_config(config: &Config, path_in: &Path, path_out: &Path) -> ReportParameters {
+        ReportParameters {
+            path_in: path_in.to_path_buf(),
+            path_out: path_out.to_path_buf(),
+            n_initial_points: config.n_initial_points,
+            n_iterations: config.n_iterations,
+            max_diff: config.max_diff,
+            skip_transparent: config.skip_transparent,
+            prefilter: config.prefilter,
+            sample_original: config.sample_original,
+            pyramid_levels: config.pyramid_levels,
+            time_budget: config.time_budget.map(|budget| budget.as_secs_f64()),
+            locate: config.locate,
+            insertion_order: config.insertion_order,
+            adaptive_strength: config.adaptive_strength,
+            adaptive_radius: config.adaptive_radius,
+            roi_strength: config.roi_strength,
+            shading: config.render.shading,
+            color_space: config.render.color_space,
+            palette: config.render.palette.clone(),
+            palette_metric: config.render.palette_metric,
+            path_mesh_in: config.path_mesh_in.clone(),
+        }
+    }
+}
+
+// How many points an iteration of the refinement inserted, at which level
+// of the pyramid, 0 being full resolution.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct IterationStatistics {
+    pub level: usize,
+    pub inserted: usize,
+    pub n_vertices: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    pub seconds: f64,
+}
+
+impl StageTiming {
+    // The time elapsed since the stage started.
+    pub fn since(stage: &'static str, start: Instant) -> StageTiming {
+        StageTiming { stage, seconds: start.elapsed().as_secs_f64() }
+    }
+}
+
+// Number of values from every lower bound up to the next one.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Histogram {
+    pub lower_bounds: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MeshStatistics {
+    pub n_vertices: usize,
+    pub n_triangles: usize,
+    // Triangle areas in pixels.
+    pub areas: Histogram,
+    // Interior angles of the triangles in degrees.
+    pub angles: Histogram,
+}
+
+impl MeshStatistics {
+    pub fn of<L: DelaunayLocateStructure<Point>>(mesh: &Mesh<L>) -> MeshStatistics {
+        let mut areas = Vec::new();
+        let mut angles = Vec::new();
+        for face in mesh.triangles() {
+            let triangle = face.as_triangle();
+            let corners: Vec<(f64, f64)> = triangle.iter().map(|vertex| (vertex.x as f64, vertex.y as f64)).collect();
+            let (a, b, c) = (corners[0], corners[1], corners[2]);
+            areas.push(((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.0);
+            for i in 0..3 {
+                let (p, q, r) = (corners[i], corners[(i + 1) % 3], corners[(i + 2) % 3]);
+                let angle = (q.1 - p.1).atan2(q.0 - p.0) - (r.1 - p.1).atan2(r.0 - p.0);
+                let angle = angle.abs().to_degrees();
+                angles.push(if angle > 180.0 { 360.0 - angle } else { angle });
+            }
+        }
+
+        // Areas double from bin to bin up to the largest triangle
+        let largest = areas.iter().cloned().fold(SMALLEST_AREA_BIN, f64::max);
+        let n_area_bins = (largest / SMALLEST_AREA_BIN).log2().floor() as usize + 2;
+        let area_bounds = (0..n_area_bins)
+            .map(|i| if i == 0 { 0.0 } else { SMALLEST_AREA_BIN * 2f64.powi(i as i32 - 1) })
+            .collect();
+        let angle_bounds = (0..(180.0 / ANGLE_BIN_WIDTH) as usize).map(|i| i as f64 * ANGLE_BIN_WIDTH).collect();
+
+        MeshStatistics {
+            n_vertices: mesh.num_vertices(),
+            n_triangles: mesh.num_triangles(),
+            areas: histogram(&areas, area_bounds),
+            angles: histogram(&angles, angle_bounds),
+        }
+    }
+}
+
+// Counts every value in the last bin whose lower bound it reaches
+fn histogram(values: &[f64], lower_bounds: Vec<f64>) -> Histogram {
+    let mut counts = vec![0; lower_bounds.len()];
+    for value in values {
+        let bin = lower_bounds.iter().rposition(|bound| value >= bound).unwrap_or(0);
+        counts[bin] += 1;
+    }
+    Histogram { lower_bounds, counts }
+}
+
+pub fn save_report(report: &Report, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut writer, report)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod report_tests {
+    use image::Rgba;
+    use crate::test_util::create_square_triangulation;
+    use super::*;
+
+    #[test]
+    fn test_mesh_statistics() {
+        let statistics = MeshStatistics::of(&create_square_triangulation(4.0, [Rgba([0.0; 4]); 4]));
+
+        assert_eq!(statistics.n_vertices, 4);
+        assert_eq!(statistics.n_triangles, 2);
+
+        // Both triangles have an area of 8 pixels
+        assert_eq!(statistics.areas.lower_bounds, vec![0.0, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0]);
+        assert_eq!(statistics.areas.counts, vec![0, 0, 0, 0, 0, 0, 2]);
+
+        // Each has two angles of 45 and one of 90 degrees
+        assert_eq!(statistics.angles.counts.len(), 18);
+        assert_eq!(statistics.angles.counts[4], 4);
+        assert_eq!(statistics.angles.counts[9], 2);
+        assert_eq!(statistics.angles.counts.iter().sum::<usize>(), 6);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = histogram(&[-1.0, 0.0, 0.5, 1.0, 7.0], vec![0.0, 1.0, 2.0]);
+        assert_eq!(histogram.counts, vec![3, 1, 1]);
+    }
+}
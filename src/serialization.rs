@@ -0,0 +1,358 @@
+// Standard library imports
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+// Community library imports
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use spade::delaunay::DelaunayLocateStructure;
+
+// Crate imports
+use crate::color::ColorSpace;
+use crate::point::Point;
+use crate::rasterization::Shading;
+use crate::Mesh;
+
+// Version of the mesh file layout, increased on every incompatible change
+pub const MESH_FORMAT_VERSION: u32 = 1;
+
+// Leading bytes of the binary mesh format
+const BINARY_MAGIC: &[u8; 4] = b"ATRI";
+
+// Largest number of bytes of the encoded parameters and largest number of
+// vertices or faces reserved up front, as the counts in a file are not
+// trusted
+const MAX_PARAMETERS_LEN: usize = 1 << 20;
+const MAX_RESERVED: usize = 1 << 16;
+
+// The parameters a mesh was created with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshParameters {
+    pub n_initial_points: usize,
+    pub n_iterations: usize,
+    pub max_diff: i32,
+    pub shading: Shading,
+    // Missing in files written before the color space was configurable
+    #[serde(default)]
+    pub color_space: ColorSpace,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vertex {
+    pub x: f32,
+    pub y: f32,
+    pub c: [f32; 4],
+}
+
+// Serializable representation of a mesh together with the dimensions of
+// the image it approximates and the parameters that were used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshFile {
+    pub version: u32,
+    pub width: u32,
+    pub height: u32,
+    pub parameters: MeshParameters,
+    pub vertices: Vec<Vertex>,
+    pub faces: Vec<[usize; 3]>,
+}
+
+impl MeshFile {
+    pub fn from_mesh<L: DelaunayLocateStructure<Point>>(
+        mesh: &Mesh<L>,
+        width: u32,
+        height: u32,
+        parameters: MeshParameters,
+    ) -> MeshFile {
+        let vertices = mesh
+            .vertices()
+            .map(|vertex| Vertex { x: vertex.x, y: vertex.y, c: vertex.c.0 })
+            .collect();
+        let faces = mesh
+            .triangles()
+            .map(|face| {
+                let triangle = face.as_triangle();
+                [triangle[0].fix(), triangle[1].fix(), triangle[2].fix()]
+            })
+            .collect();
+
+        MeshFile { version: MESH_FORMAT_VERSION, width, height, parameters, vertices, faces }
+    }
+
+    // Rebuilds the mesh by inserting the vertices in their original order and
+    // checks that it has the saved faces
+    pub fn to_mesh<L: DelaunayLocateStructure<Point>>(&self) -> io::Result<Mesh<L>> {
+        let mut delaunay = Mesh::new();
+        for vertex in self.vertices.iter() {
+            delaunay.insert(Point::new(vertex.x, vertex.y, Rgba(vertex.c)));
+        }
+
+        let rebuilt: Vec<[usize; 3]> = delaunay
+            .triangles()
+            .map(|face| {
+                let triangle = face.as_triangle();
+                [triangle[0].fix(), triangle[1].fix(), triangle[2].fix()]
+            })
+            .collect();
+        if normalized_faces(&rebuilt) != normalized_faces(&self.faces) {
+            return Err(invalid_data("the faces do not match the triangulation of the vertices".to_string()));
+        }
+        Ok(delaunay)
+    }
+}
+
+// The faces starting at their smallest index, keeping the orientation, in
+// ascending order, to compare faces listed in a different order
+fn normalized_faces(faces: &[[usize; 3]]) -> Vec<[usize; 3]> {
+    let mut normalized: Vec<[usize; 3]> = faces
+        .iter()
+        .map(|face| {
+            let first = (0..3).min_by_key(|&i| face[i]).unwrap();
+            [face[first], face[(first + 1) % 3], face[(first + 2) % 3]]
+        })
+        .collect();
+    normalized.sort_unstable();
+    normalized
+}
+
+// Saves the mesh file as JSON if the path ends with `.json` and in the
+// compact binary format otherwise.
+pub fn save_mesh(mesh_file: &MeshFile, path: &Path) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    if is_json_path(path) {
+        serde_json::to_writer(&mut writer, mesh_file)?;
+    } else {
+        write_binary(mesh_file, &mut writer)?;
+    }
+    writer.flush()
+}
+
+// Loads a mesh file written by `save_mesh`.
+pub fn load_mesh(path: &Path) -> io::Result<MeshFile> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mesh_file: MeshFile = if is_json_path(path) {
+        serde_json::from_reader(reader)?
+    } else {
+        read_binary(&mut reader)?
+    };
+
+    check_version(mesh_file)
+}
+
+fn check_version(mesh_file: MeshFile) -> io::Result<MeshFile> {
+    match mesh_file.version {
+        MESH_FORMAT_VERSION => Ok(mesh_file),
+        version => Err(invalid_data(format!(
+            "unsupported mesh format version {} (expected {})",
+            version, MESH_FORMAT_VERSION
+        ))),
+    }
+}
+
+fn is_json_path(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("json")
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// Binary layout (little endian): magic, version, width, height, the length
+// of the JSON encoded parameters followed by the parameters, the number of
+// vertices followed by x, y and RGBA of every vertex, the number of faces
+// followed by the three vertex indices of every face.
+pub fn write_binary<W: Write>(mesh_file: &MeshFile, w: &mut W) -> io::Result<()> {
+    let parameters = serde_json::to_vec(&mesh_file.parameters)?;
+
+    w.write_all(BINARY_MAGIC)?;
+    w.write_all(&mesh_file.version.to_le_bytes())?;
+    w.write_all(&mesh_file.width.to_le_bytes())?;
+    w.write_all(&mesh_file.height.to_le_bytes())?;
+    w.write_all(&(parameters.len() as u32).to_le_bytes())?;
+    w.write_all(&parameters)?;
+
+    w.write_all(&(mesh_file.vertices.len() as u32).to_le_bytes())?;
+    for vertex in mesh_file.vertices.iter() {
+        w.write_all(&vertex.x.to_le_bytes())?;
+        w.write_all(&vertex.y.to_le_bytes())?;
+        for c in vertex.c.iter() {
+            w.write_all(&c.to_le_bytes())?;
+        }
+    }
+
+    w.write_all(&(mesh_file.faces.len() as u32).to_le_bytes())?;
+    for face in mesh_file.faces.iter() {
+        for index in face.iter() {
+            w.write_all(&(*index as u32).to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// Reads the binary layout, failing with invalid data if the file is cut
+// short or claims more data than it contains
+pub fn read_binary<R: Read>(r: &mut R) -> io::Result<MeshFile> {
+    read_binary_content(r).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => invalid_data("the mesh file is truncated".to_string()),
+        _ => err,
+    })
+}
+
+fn read_binary_content<R: Read>(r: &mut R) -> io::Result<MeshFile> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != BINARY_MAGIC {
+        return Err(invalid_data("not an AdapTri binary mesh file".to_string()));
+    }
+
+    let version = read_u32(r)?;
+    let width = read_u32(r)?;
+    let height = read_u32(r)?;
+
+    let parameters_len = read_u32(r)? as usize;
+    if parameters_len > MAX_PARAMETERS_LEN {
+        return Err(invalid_data(format!("the parameters are too long ({} bytes)", parameters_len)));
+    }
+    let mut parameters = vec![0; parameters_len];
+    r.read_exact(&mut parameters)?;
+    let parameters = serde_json::from_slice(&parameters)?;
+
+    let n_vertices = read_u32(r)? as usize;
+    let mut vertices = Vec::with_capacity(n_vertices.min(MAX_RESERVED));
+    for _ in 0..n_vertices {
+        let x = read_f32(r)?;
+        let y = read_f32(r)?;
+        let mut c = [0.0; 4];
+        for channel in c.iter_mut() {
+            *channel = read_f32(r)?;
+        }
+        vertices.push(Vertex { x, y, c });
+    }
+
+    let n_faces = read_u32(r)? as usize;
+    let mut faces = Vec::with_capacity(n_faces.min(MAX_RESERVED));
+    for _ in 0..n_faces {
+        faces.push([read_u32(r)? as usize, read_u32(r)? as usize, read_u32(r)? as usize]);
+    }
+
+    Ok(MeshFile { version, width, height, parameters, vertices, faces })
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod serialization_tests {
+    use spade::delaunay::DelaunayWalkLocate;
+    use crate::test_util::create_square_triangulation;
+    use super::*;
+
+    fn create_mesh_file() -> MeshFile {
+        let parameters = MeshParameters {
+            n_initial_points: 4,
+            n_iterations: 2,
+            max_diff: 15,
+            shading: Shading::Gouraud,
+            color_space: ColorSpace::Linear,
+        };
+        let corners =
+            [Rgba([1.0, 0.0, 0.0, 1.0]), Rgba([0.0, 1.0, 0.0, 1.0]), Rgba([0.0, 0.0, 1.0, 1.0]), Rgba([0.1, 0.2, 0.3, 0.4])];
+        MeshFile::from_mesh(&create_square_triangulation(10.0, corners), 11, 11, parameters)
+    }
+
+    #[test]
+    fn test_from_mesh() {
+        let mesh_file = create_mesh_file();
+
+        assert_eq!(mesh_file.version, MESH_FORMAT_VERSION);
+        assert_eq!(mesh_file.vertices.len(), 4);
+        assert_eq!(mesh_file.faces.len(), 2);
+        assert_eq!(mesh_file.vertices[3], Vertex { x: 10.0, y: 10.0, c: [0.1, 0.2, 0.3, 0.4] });
+    }
+
+    #[test]
+    fn test_to_mesh() {
+        let mesh_file = create_mesh_file();
+        let mesh: Mesh = mesh_file.to_mesh().unwrap();
+        let rebuilt = MeshFile::from_mesh(&mesh, 11, 11, mesh_file.parameters.clone());
+        assert_eq!(rebuilt, mesh_file);
+
+        // Faces listed in another order or starting at another vertex match
+        let mut reordered = mesh_file.clone();
+        reordered.faces.reverse();
+        reordered.faces[0].rotate_left(1);
+        assert!(reordered.to_mesh::<DelaunayWalkLocate>().is_ok());
+
+        let mut flipped = mesh_file;
+        flipped.faces[0].swap(0, 1);
+        let err = flipped.to_mesh::<DelaunayWalkLocate>().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mesh_file = create_mesh_file();
+        let json = serde_json::to_string(&mesh_file).unwrap();
+
+        assert!(json.contains("\"shading\":\"gouraud\""));
+        assert_eq!(serde_json::from_str::<MeshFile>(&json).unwrap(), mesh_file);
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let mesh_file = create_mesh_file();
+        let mut buffer = Vec::new();
+        write_binary(&mesh_file, &mut buffer).unwrap();
+
+        assert_eq!(&buffer[0..4], BINARY_MAGIC);
+        assert_eq!(read_binary(&mut buffer.as_slice()).unwrap(), mesh_file);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut mesh_file = create_mesh_file();
+        mesh_file.version = MESH_FORMAT_VERSION + 1;
+        assert_eq!(check_version(mesh_file).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_binary_truncated() {
+        let mut buffer = Vec::new();
+        write_binary(&create_mesh_file(), &mut buffer).unwrap();
+
+        let truncated = &buffer[..buffer.len() - 5];
+        let err = read_binary(&mut &truncated[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A count of 0xFFFFFFFF vertices is not allocated up front
+        let params_len = u32::from_le_bytes([buffer[16], buffer[17], buffer[18], buffer[19]]);
+        let n_vertices_offset = 20 + params_len as usize;
+        buffer.truncate(n_vertices_offset);
+        buffer.extend_from_slice(&u32::MAX.to_le_bytes());
+        buffer.extend_from_slice(&[0; 24]);
+        let err = read_binary(&mut buffer.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Neither are parameters of 0xFFFFFFFF bytes
+        buffer[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = read_binary(&mut buffer.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_binary_wrong_magic() {
+        let buffer = b"PNG\0\0\0\0\0".to_vec();
+        let err = read_binary(&mut buffer.as_slice()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
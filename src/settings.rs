@@ -0,0 +1,308 @@
+// Standard library imports
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Community library imports
+use clap::ArgMatches;
+use toml::{Table, Value};
+
+// Built-in parameter sets selectable with `--preset`, written like
+// configuration files.
+pub const PRESETS: [(&str, &str); 4] = [
+    (
+        "poster-fine",
+        r#"
+        points = 1000
+        iterations = 8
+        max_diff = 8
+        color_space = "oklab"
+        shading = "gouraud"
+        prefilter = "bilateral"
+        sample_original = true
+        "#,
+    ),
+    (
+        "thumbnail-coarse",
+        r#"
+        points = 50
+        iterations = 3
+        max_diff = 30
+        pyramid = 2
+        prefilter = "gaussian:1.5"
+        "#,
+    ),
+    (
+        "low-poly",
+        r#"
+        points = 200
+        iterations = 4
+        max_diff = 25
+        color_space = "oklab"
+        palette = "12"
+        "#,
+    ),
+    (
+        "photo-denoise",
+        r#"
+        iterations = 6
+        prefilter = "median:2"
+        sample_original = true
+        adaptive = 0.5
+        "#,
+    ),
+];
+
+// Options that take several values, given as arrays in configuration files
+const MULTIPLE_VALUES: [&str; 4] = ["file", "export", "roi_rect", "roi_polygon"];
+
+// The command line arguments on top of a configuration file on top of a
+// preset. Options are named as in `--help`, with `-` or `_` between words.
+pub struct Settings<'a> {
+    matches: &'a ArgMatches<'a>,
+    // Highest priority first
+    layers: Vec<Table>,
+    // Every option looked up, to find unknown keys in the layers
+    used: RefCell<BTreeSet<String>>,
+}
+
+impl<'a> Settings<'a> {
+    pub fn new(matches: &'a ArgMatches<'a>, config: Option<&Path>, preset: Option<&str>) -> Result<Settings<'a>, String> {
+        let mut layers = Vec::new();
+        if let Some(path) = config {
+            let content = fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            layers.push(parse_table(&content).map_err(|err| format!("{}: {}", path.display(), err))?);
+        }
+        if let Some(name) = preset {
+            let (_, content) = PRESETS
+                .iter()
+                .find(|(preset, _)| *preset == name)
+                .ok_or_else(|| format!("unknown preset '{}'", name))?;
+            layers.push(parse_table(content).unwrap());
+        }
+        Ok(Settings { matches, layers, used: RefCell::new(BTreeSet::new()) })
+    }
+
+    // The value with the highest priority that is not the default of the
+    // command line argument
+    fn layered_value(&self, name: &str) -> Option<&Value> {
+        self.used.borrow_mut().insert(name.to_string());
+        if self.matches.occurrences_of(name) > 0 {
+            return None;
+        }
+        self.layers.iter().find_map(|layer| layer.get(name))
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<String> {
+        match self.layered_value(name) {
+            Some(value) => Some(to_string(value)),
+            None => self.matches.value_of(name).map(String::from),
+        }
+    }
+
+    pub fn values_of(&self, name: &str) -> Option<Vec<String>> {
+        match self.layered_value(name) {
+            Some(Value::Array(values)) => Some(values.iter().map(to_string).collect()),
+            Some(value) => Some(vec![to_string(value)]),
+            None => self.matches.values_of(name).map(|values| values.map(String::from).collect()),
+        }
+    }
+
+    // The value parsed as the given type, naming the option if it is invalid
+    pub fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value_of(name) {
+            Some(value) => match value.parse::<T>() {
+                Ok(value) => Ok(Some(value)),
+                Err(_) => Err(format!("invalid value '{}' for option '{}'", value, name)),
+            },
+            None => Ok(None),
+        }
+    }
+
+    // A flag is turned off on the command line by its `--no-` counterpart,
+    // overriding the layers
+    pub fn is_present(&self, name: &str) -> bool {
+        let value = self.layered_value(name);
+        if self.matches.is_present(format!("no_{}", name)) {
+            return false;
+        }
+        match value {
+            Some(value) => value.as_bool().unwrap_or(false),
+            None => self.matches.is_present(name),
+        }
+    }
+
+    // Checks that every option given by any layer has the option it
+    // requires, as clap only checks the command line itself.
+    pub fn check_requirements(&self, requirements: &[(&str, &str)]) -> Result<(), String> {
+        for (name, required) in requirements.iter() {
+            if self.is_present(name) && !self.is_present(required) && self.value_of(required).is_none() {
+                return Err(format!(
+                    "--{} requires --{}",
+                    name.replace('_', "-"),
+                    required.replace('_', "-")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Keys of the configuration file or preset that no option was looked
+    // up by, which are most likely misspelled.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let used = self.used.borrow();
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.keys())
+            .filter(|key| !used.contains(*key))
+            .cloned()
+            .collect()
+    }
+}
+
+// Parses the configuration with the words of the keys separated by `_`,
+// rejecting tables and arrays for options that take a single value
+fn parse_table(content: &str) -> Result<Table, String> {
+    let table: Table = content.parse().map_err(|err: toml::de::Error| err.message().to_string())?;
+    let mut normalized = Table::new();
+    for (key, value) in table {
+        let key = key.replace('-', "_");
+        let valid = match &value {
+            Value::Table(_) | Value::Datetime(_) => false,
+            Value::Array(values) => {
+                MULTIPLE_VALUES.contains(&key.as_str())
+                    && values.iter().all(|value| !matches!(value, Value::Array(_) | Value::Table(_)))
+            }
+            _ => true,
+        };
+        if !valid {
+            return Err(format!("invalid value for '{}'", key));
+        }
+        normalized.insert(key, value);
+    }
+    Ok(normalized)
+}
+
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod settings_tests {
+    use clap::{App, Arg};
+    use super::*;
+
+    fn create_matches(args: &[&str]) -> ArgMatches<'static> {
+        App::new("test")
+            .arg(Arg::with_name("points").long("points").takes_value(true).default_value("100"))
+            .arg(Arg::with_name("iterations").long("iterations").takes_value(true).default_value("4"))
+            .arg(Arg::with_name("max_diff").long("max_diff").takes_value(true).default_value("15"))
+            .arg(Arg::with_name("export").long("export").takes_value(true).multiple(true).number_of_values(1))
+            .arg(Arg::with_name("dither").long("dither"))
+            .arg(Arg::with_name("no_dither").long("no-dither"))
+            .arg(Arg::with_name("palette").long("palette").takes_value(true))
+            .get_matches_from(std::iter::once("test").chain(args.iter().cloned()))
+    }
+
+    fn create_settings<'a>(matches: &'a ArgMatches<'a>, content: &str) -> Settings<'a> {
+        Settings { matches, layers: vec![parse_table(content).unwrap()], used: RefCell::new(BTreeSet::new()) }
+    }
+
+    #[test]
+    fn test_priorities() {
+        let matches = create_matches(&["--points", "50"]);
+        let settings = create_settings(&matches, "points = 500\nmax-diff = 20\ndither = true");
+
+        // Given arguments override the file, the file overrides defaults
+        assert_eq!(settings.value_of("points"), Some("50".to_string()));
+        assert_eq!(settings.value_of("max_diff"), Some("20".to_string()));
+        assert!(settings.is_present("dither"));
+
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "");
+        assert_eq!(settings.value_of("points"), Some("100".to_string()));
+        assert!(!settings.is_present("dither"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "points = 500");
+        assert_eq!(settings.parse::<usize>("points"), Ok(Some(500)));
+        assert_eq!(settings.parse::<usize>("iterations"), Ok(Some(4)));
+        assert_eq!(settings.parse::<usize>("palette"), Ok(None));
+
+        // Negative counts are rejected instead of wrapping around
+        let settings = create_settings(&matches, "iterations = -1");
+        assert_eq!(
+            settings.parse::<usize>("iterations"),
+            Err("invalid value '-1' for option 'iterations'".to_string())
+        );
+        let matches = create_matches(&["--iterations=-1"]);
+        let settings = create_settings(&matches, "");
+        assert!(settings.parse::<usize>("iterations").is_err());
+    }
+
+    #[test]
+    fn test_negated_flags() {
+        let matches = create_matches(&["--no-dither"]);
+        let settings = create_settings(&matches, "dither = true");
+        assert!(!settings.is_present("dither"));
+        assert!(settings.unknown_keys().is_empty());
+
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "dither = false");
+        assert!(!settings.is_present("dither"));
+    }
+
+    #[test]
+    fn test_requirements() {
+        let requirements = [("dither", "palette")];
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "dither = true");
+        assert_eq!(settings.check_requirements(&requirements), Err("--dither requires --palette".to_string()));
+
+        let settings = create_settings(&matches, "dither = true\npalette = \"8\"");
+        assert!(settings.check_requirements(&requirements).is_ok());
+
+        let matches = create_matches(&["--palette", "8"]);
+        let settings = create_settings(&matches, "dither = true");
+        assert!(settings.check_requirements(&requirements).is_ok());
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "export = [\"a.vtk\", \"a.glb\"]");
+        assert_eq!(settings.values_of("export"), Some(vec!["a.vtk".to_string(), "a.glb".to_string()]));
+
+        let settings = create_settings(&matches, "export = \"a.vtk\"");
+        assert_eq!(settings.values_of("export"), Some(vec!["a.vtk".to_string()]));
+
+        assert!(parse_table("points = [1, 2]").is_err());
+        assert!(parse_table("[points]\nvalue = 1").is_err());
+        assert!(parse_table("points = ").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let matches = create_matches(&[]);
+        let settings = create_settings(&matches, "points = 500\npionts = 5");
+        settings.value_of("points");
+        assert_eq!(settings.unknown_keys(), vec!["pionts".to_string()]);
+    }
+
+    #[test]
+    fn test_presets() {
+        let matches = create_matches(&[]);
+        for (name, _) in PRESETS.iter() {
+            assert!(Settings::new(&matches, None, Some(name)).is_ok());
+        }
+        assert!(Settings::new(&matches, None, Some("unknown")).is_err());
+    }
+}
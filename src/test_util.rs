@@ -0,0 +1,26 @@
+// Community library imports
+use image::Rgba;
+
+// Crate imports
+use crate::point::Point;
+use crate::Mesh;
+
+// A single triangle with a red, a green and a blue corner
+pub fn create_simple_triangulation() -> Mesh {
+    let mut delaunay = Mesh::new();
+    delaunay.insert(Point::new(0.0, 0.0, Rgba([1.0, 0.0, 0.0, 1.0])));
+    delaunay.insert(Point::new(10.0, 0.0, Rgba([0.0, 1.0, 0.0, 1.0])));
+    delaunay.insert(Point::new(0.0, 10.0, Rgba([0.0, 0.0, 1.0, 1.0])));
+    delaunay
+}
+
+// A square of the given size split into two triangles, with the colors of
+// the corners at (0, 0), (size, 0), (0, size) and (size, size)
+pub fn create_square_triangulation(size: f32, colors: [Rgba<f32>; 4]) -> Mesh {
+    let mut delaunay = Mesh::new();
+    delaunay.insert(Point::new(0.0, 0.0, colors[0]));
+    delaunay.insert(Point::new(size, 0.0, colors[1]));
+    delaunay.insert(Point::new(0.0, size, colors[2]));
+    delaunay.insert(Point::new(size, size, colors[3]));
+    delaunay
+}
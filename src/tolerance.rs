@@ -0,0 +1,113 @@
+// Community library imports
+use image::Rgba32FImage;
+
+// Bounds of the factor, so that neither textured nor smooth regions end up
+// without any refinement or with a triangle per pixel
+const MIN_FACTOR: f32 = 0.25;
+const MAX_FACTOR: f32 = 4.0;
+
+// Keeps the ratio of deviations finite in perfectly flat regions
+const EPSILON: f64 = 1.0 / 255.0;
+
+// Per-pixel factor of the refinement threshold derived from the local
+// contrast of the image: textured regions tolerate larger differences, smooth
+// gradients smaller ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToleranceMap {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl ToleranceMap {
+    // Compares the standard deviation of the luminance in the window of the
+    // given radius around every pixel with its mean over the image.
+    pub fn from_local_contrast(img: &Rgba32FImage, radius: u32, strength: f32) -> ToleranceMap {
+        let (width, height) = img.dimensions();
+        let (w, h) = (width as usize, height as usize);
+
+        // Summed-area tables of the luminance and its square, with an extra
+        // row and column of zeros
+        let mut sum = vec![0.0f64; (w + 1) * (h + 1)];
+        let mut sum_squared = vec![0.0f64; (w + 1) * (h + 1)];
+        for (x, y, c) in img.enumerate_pixels() {
+            let luminance = (c[3] * (0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2])) as f64;
+            let (x, y) = (x as usize, y as usize);
+            let i = (y + 1) * (w + 1) + x + 1;
+            sum[i] = luminance + sum[i - 1] + sum[i - w - 1] - sum[i - w - 2];
+            sum_squared[i] = luminance * luminance + sum_squared[i - 1] + sum_squared[i - w - 1] - sum_squared[i - w - 2];
+        }
+        let window_sum = |table: &[f64], x0: usize, y0: usize, x1: usize, y1: usize| {
+            table[y1 * (w + 1) + x1] - table[y0 * (w + 1) + x1] - table[y1 * (w + 1) + x0] + table[y0 * (w + 1) + x0]
+        };
+
+        let radius = radius as usize;
+        let mut deviations = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let (x0, y0) = (x.saturating_sub(radius), y.saturating_sub(radius));
+                let (x1, y1) = ((x + radius + 1).min(w), (y + radius + 1).min(h));
+                let n = ((x1 - x0) * (y1 - y0)) as f64;
+                let mean = window_sum(&sum, x0, y0, x1, y1) / n;
+                let variance = window_sum(&sum_squared, x0, y0, x1, y1) / n - mean * mean;
+                deviations.push(variance.max(0.0).sqrt());
+            }
+        }
+
+        let mean_deviation = deviations.iter().sum::<f64>() / deviations.len() as f64;
+        let values = deviations
+            .iter()
+            .map(|deviation| {
+                let ratio = (deviation + EPSILON) / (mean_deviation + EPSILON);
+                (ratio.powf(strength as f64) as f32).clamp(MIN_FACTOR, MAX_FACTOR)
+            })
+            .collect();
+        ToleranceMap { width, height, values }
+    }
+
+    pub fn get(&self, x: f32, y: f32) -> f32 {
+        let col = (x.max(0.0) as u32).min(self.width - 1);
+        let row = (y.max(0.0) as u32).min(self.height - 1);
+        self.values[(row * self.width + col) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tolerance_tests {
+    use image::Rgba;
+    use super::*;
+
+    // Left half checkered, right half flat gray
+    fn create_half_textured_image() -> Rgba32FImage {
+        Rgba32FImage::from_fn(20, 10, |x, y| {
+            if x < 10 && (x + y) % 2 == 0 {
+                Rgba([1.0, 1.0, 1.0, 1.0])
+            } else if x < 10 {
+                Rgba([0.0, 0.0, 0.0, 1.0])
+            } else {
+                Rgba([0.5, 0.5, 0.5, 1.0])
+            }
+        })
+    }
+
+    #[test]
+    fn test_flat_image() {
+        let img = Rgba32FImage::from_pixel(8, 8, Rgba([0.3, 0.6, 0.9, 1.0]));
+        let map = ToleranceMap::from_local_contrast(&img, 2, 1.0);
+
+        assert!(map.values.iter().all(|value| (value - 1.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_local_contrast() {
+        let img = create_half_textured_image();
+        let map = ToleranceMap::from_local_contrast(&img, 1, 1.0);
+
+        assert!(map.get(2.0, 5.0) > 1.0);
+        assert_eq!(map.get(17.0, 5.0), MIN_FACTOR);
+
+        // Without strength there is no adaptation
+        let map = ToleranceMap::from_local_contrast(&img, 1, 0.0);
+        assert!(map.values.iter().all(|value| *value == 1.0));
+    }
+}
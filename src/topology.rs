@@ -36,24 +36,24 @@ mod topology_tests {
 
     #[test]
     fn test_point_in_triangle() {
-        let point_in = Point::new(5.0, 5.0, Rgba([255, 255, 255, 255]));
-        let point_out = Point::new(15.0, 15.0, Rgba([255, 255, 255, 255]));
+        let point_in = Point::new(5.0, 5.0, Rgba([1.0, 1.0, 1.0, 1.0]));
+        let point_out = Point::new(15.0, 15.0, Rgba([1.0, 1.0, 1.0, 1.0]));
 
         let mut delaunay = FloatDelaunayTriangulation::with_walk_locate();
         delaunay.insert(Point {
             x: 0.0,
             y: 0.0,
-            c: Rgba([255, 0, 0, 255]),
+            c: Rgba([1.0, 0.0, 0.0, 1.0]),
         });
         delaunay.insert(Point {
             x: 10.0,
             y: 0.0,
-            c: Rgba([0, 255, 0, 255]),
+            c: Rgba([0.0, 1.0, 0.0, 1.0]),
         });
         delaunay.insert(Point {
             x: 10.0,
             y: 10.0,
-            c: Rgba([0, 0, 255, 255]),
+            c: Rgba([0.0, 0.0, 1.0, 1.0]),
         });
 
         for face in delaunay.triangles() {
@@ -69,9 +69,9 @@ mod topology_tests {
 
     #[test]
     fn test_is_ccw() {
-        let p1 = Point::new(0.0, 0.0, Rgba([0; 4]));
-        let p2 = Point::new(1.0, 0.0, Rgba([0; 4]));
-        let p3 = Point::new(0.0, 1.0, Rgba([0; 4]));
+        let p1 = Point::new(0.0, 0.0, Rgba([0.0; 4]));
+        let p2 = Point::new(1.0, 0.0, Rgba([0.0; 4]));
+        let p3 = Point::new(0.0, 1.0, Rgba([0.0; 4]));
 
         assert_eq!(is_ccw(&p1, &p2, &p3), true);
         assert_eq!(is_ccw(&p3, &p2, &p1), false);
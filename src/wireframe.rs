@@ -0,0 +1,192 @@
+// Standard library imports
+use std::str::FromStr;
+
+// Community library imports
+use image::{Rgba, Rgba32FImage};
+use spade::delaunay::DelaunayLocateStructure;
+
+// Crate imports
+use crate::color::blend_over;
+use crate::point::Point;
+use crate::Mesh;
+
+// Whether the wireframe is drawn on top of the filled triangles or alone.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WireframeMode {
+    Overlay,
+    Only,
+}
+
+impl FromStr for WireframeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<WireframeMode, String> {
+        match s {
+            "overlay" => Ok(WireframeMode::Overlay),
+            "only" => Ok(WireframeMode::Only),
+            _ => Err(format!("unknown wireframe mode '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WireframeStyle {
+    pub color: Rgba<f32>,
+    // Width of the edges in pixels.
+    pub width: f32,
+    pub opacity: f32,
+    pub anti_aliasing: bool,
+    // Radius of the dots drawn at the vertices, no dots are drawn if zero.
+    pub vertex_radius: f32,
+}
+
+// Draws the edges and optionally the vertices of the mesh onto the image.
+pub fn draw_wireframe<L: DelaunayLocateStructure<Point>>(
+    img: &mut Rgba32FImage,
+    mesh: &Mesh<L>,
+    style: &WireframeStyle,
+) {
+    let (width, height) = img.dimensions();
+    let mut coverage = vec![0.0f32; (width * height) as usize];
+
+    let half_width = style.width / 2.0;
+    for edge in mesh.edges() {
+        let (from, to) = (*edge.from(), *edge.to());
+        let bbox = [from.x.min(to.x), from.x.max(to.x), from.y.min(to.y), from.y.max(to.y)];
+        add_coverage(&mut coverage, width, height, bbox, half_width, style.anti_aliasing, |x, y| {
+            distance_to_segment(x, y, &from, &to)
+        });
+    }
+
+    if style.vertex_radius > 0.0 {
+        for vertex in mesh.vertices() {
+            let center = *vertex;
+            let bbox = [center.x, center.x, center.y, center.y];
+            add_coverage(&mut coverage, width, height, bbox, style.vertex_radius, style.anti_aliasing, |x, y| {
+                ((x - center.x).powi(2) + (y - center.y).powi(2)).sqrt()
+            });
+        }
+    }
+
+    for (i, pixel) in img.pixels_mut().enumerate() {
+        if coverage[i] > 0.0 {
+            *pixel = blend_over(*pixel, style.color, coverage[i] * style.opacity);
+        }
+    }
+}
+
+// Rasterizes a stroke given by its distance function within the bounding box
+// [xmin, xmax, ymin, ymax] of its skeleton, keeping the highest coverage of
+// every pixel.
+fn add_coverage<F>(
+    coverage: &mut [f32],
+    width: u32,
+    height: u32,
+    bbox: [f32; 4],
+    radius: f32,
+    anti_aliasing: bool,
+    distance: F,
+) where
+    F: Fn(f32, f32) -> f32,
+{
+    let margin = radius + 1.0;
+    let col_min = (bbox[0] - margin).floor().max(0.0) as u32;
+    let col_max = ((bbox[1] + margin).ceil().max(0.0) as u32).min(width.saturating_sub(1));
+    let row_min = (bbox[2] - margin).floor().max(0.0) as u32;
+    let row_max = ((bbox[3] + margin).ceil().max(0.0) as u32).min(height.saturating_sub(1));
+
+    for row in row_min..=row_max {
+        for col in col_min..=col_max {
+            let d = distance(col as f32, row as f32);
+            let value = if anti_aliasing {
+                (radius + 0.5 - d).clamp(0.0, 1.0)
+            } else if d <= radius {
+                1.0
+            } else {
+                0.0
+            };
+            let index = (row * width + col) as usize;
+            coverage[index] = coverage[index].max(value);
+        }
+    }
+}
+
+fn distance_to_segment(x: f32, y: f32, a: &Point, b: &Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((x - a.x) * dx + (y - a.y) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (px, py) = (a.x + t * dx, a.y + t * dy);
+    ((x - px).powi(2) + (y - py).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod wireframe_tests {
+    use crate::test_util::create_simple_triangulation;
+    use super::*;
+
+    const WHITE: Rgba<f32> = Rgba([1.0, 1.0, 1.0, 1.0]);
+    const BLACK: Rgba<f32> = Rgba([0.0, 0.0, 0.0, 1.0]);
+
+    fn create_style() -> WireframeStyle {
+        WireframeStyle { color: BLACK, width: 1.0, opacity: 1.0, anti_aliasing: false, vertex_radius: 0.0 }
+    }
+
+    #[test]
+    fn test_wireframe_mode_from_str() {
+        assert_eq!("overlay".parse::<WireframeMode>(), Ok(WireframeMode::Overlay));
+        assert_eq!("only".parse::<WireframeMode>(), Ok(WireframeMode::Only));
+        assert!("under".parse::<WireframeMode>().is_err());
+    }
+
+    #[test]
+    fn test_distance_to_segment() {
+        let a = Point::new(0.0, 0.0, WHITE);
+        let b = Point::new(10.0, 0.0, WHITE);
+
+        assert_eq!(distance_to_segment(5.0, 3.0, &a, &b), 3.0);
+        assert_eq!(distance_to_segment(-3.0, 4.0, &a, &b), 5.0);
+        assert_eq!(distance_to_segment(3.0, 4.0, &a, &a), 5.0);
+    }
+
+    #[test]
+    fn test_draw_wireframe() {
+        let mesh = create_simple_triangulation();
+        let mut img = Rgba32FImage::from_pixel(12, 12, WHITE);
+        draw_wireframe(&mut img, &mesh, &create_style());
+
+        assert_eq!(*img.get_pixel(5, 0), BLACK);
+        assert_eq!(*img.get_pixel(0, 5), BLACK);
+        assert_eq!(*img.get_pixel(5, 5), BLACK);
+        assert_eq!(*img.get_pixel(2, 2), WHITE);
+        assert_eq!(*img.get_pixel(11, 11), WHITE);
+    }
+
+    #[test]
+    fn test_draw_wireframe_opacity_and_dots() {
+        let mesh = create_simple_triangulation();
+        let mut img = Rgba32FImage::from_pixel(12, 12, WHITE);
+        let style = WireframeStyle { opacity: 0.5, vertex_radius: 2.0, ..create_style() };
+        draw_wireframe(&mut img, &mesh, &style);
+
+        // Where edges and dots overlap, the color is blended only once
+        assert_eq!(*img.get_pixel(0, 0), Rgba([0.5, 0.5, 0.5, 1.0]));
+        assert_eq!(*img.get_pixel(1, 1), Rgba([0.5, 0.5, 0.5, 1.0]));
+        assert_eq!(*img.get_pixel(3, 3), WHITE);
+    }
+
+    #[test]
+    fn test_draw_wireframe_anti_aliased() {
+        let mesh = create_simple_triangulation();
+        let mut img = Rgba32FImage::from_pixel(12, 12, WHITE);
+        let style = WireframeStyle { anti_aliasing: true, ..create_style() };
+        draw_wireframe(&mut img, &mesh, &style);
+
+        assert_eq!(*img.get_pixel(5, 0), BLACK);
+        let partially_covered = img.get_pixel(6, 5)[0];
+        assert!(partially_covered > 0.0 && partially_covered < 1.0);
+    }
+}